@@ -1,190 +1,371 @@
-use crate::bmp::*;
 use crate::configuration::*;
 use crate::dpad::*;
 use crate::gambatte::*;
 use crate::gfx::*;
+use crate::history::*;
+use crate::input_log::*;
 use crate::key::*;
+use crate::keymap::*;
+use crate::layout::*;
+use crate::notification::*;
+use crate::palette::*;
 use crate::platform::*;
-use crate::winapi::*;
+use crate::skin::*;
+use crate::stats::*;
 
-use std::thread;
 use std::time::*;
 
-pub const KEY_SIZE: i32 = 34;
-pub const ARROW_SIZE: i32 = 14;
-pub const CHARACTER_SIZE: i32 = 16;
-
-pub const WIDTH: i32 = KEY_SIZE * 9;
-pub const HEIGHT: i32 = KEY_SIZE * 8;
-pub const SCALE: i32 = 1;
+pub const DEFAULT_SCALE: i32 = 1;
+pub const MAX_SCALE: i32 = 6;
 pub const TITLE: &str = "Input Display";
 
-pub const UP: usize = 0;
-pub const DOWN: usize = 1;
-pub const LEFT: usize = 2;
-pub const RIGHT: usize = 3;
-pub const POWER: usize = 8;
+const POWER: &str = "POWER";
+
+const MENU_SYNC_GAMBATTE: u32 = 1;
+const MENU_SET_KEYBINDS: u32 = 2;
+const MENU_ADD_PALETTE: u32 = 3;
+const MENU_RENAME_PALETTE: u32 = 4;
+const MENU_DELETE_PALETTE: u32 = 5;
+const MENU_IMPORT_PALETTES: u32 = 6;
+const MENU_EXPORT_PALETTES: u32 = 7;
+const MENU_INPUT_LOG: u32 = 8;
+const MENU_PRESS_COUNTS: u32 = 9;
+const MENU_RESET_STATS: u32 = 10;
+const MENU_SCALE: u32 = 20;
+const MENU_PALETTE: u32 = 100;
+
+const SYNC_MESSAGE_DURATION: Duration = Duration::from_secs(3);
 
 pub struct Application {
-    pub platform: Platform,
+    pub platform: Box<dyn Platform>,
+    pub offscreen_buffer: BackBuffer,
     pub keyset: SpriteSheet,
     pub arrowset: SpriteSheet,
-    pub font: SpriteSheet,
+    pub font: Font,
     pub palettes: Vec<(String, Palette)>,
     pub palette: Palette,
     pub palette_index: usize,
     pub keys: Vec<Key>,
     pub dpad: Vec<DpadKey>,
+    pub dpad_center: Option<(f32, f32, i32)>,
+    pub text_row: f32,
     pub key_to_configure: i32,
-    pub text_buffer: String,
+    pub palette_name: Option<String>,
+    pub notifications: Notifications,
+    pub notification_deadline: Option<Duration>,
+    pub history: InputHistory,
+    pub input_log: InputLog,
+    pub show_press_counts: bool,
+    pub stats_since: Duration,
     pub gambatte_sync: bool,
+    pub input_device: Option<String>,
+    pub gambatte_config: Option<String>,
+    pub layout_path: Option<String>,
+    pub skin_path: Option<String>,
+    pub scale: i32,
+}
+
+impl Application {
+    pub fn new(platform: Box<dyn Platform>, layout: &Layout, skin: Skin, history: InputHistory) -> Application {
+        let (width, height) = window_size(layout, &skin, &history);
+        let mut app = Application {
+            platform: platform,
+            offscreen_buffer: BackBuffer::new(width, height),
+            keyset: skin.keyset,
+            arrowset: skin.arrowset,
+            font: skin.font,
+            palettes: built_in_palettes(),
+            palette: Vec::new(),
+            palette_index: 0,
+            keys: Vec::new(),
+            dpad: Vec::new(),
+            dpad_center: layout.dpad_center,
+            text_row: layout.text_row,
+            key_to_configure: -1,
+            palette_name: None,
+            notifications: Notifications::new(),
+            notification_deadline: None,
+            history: history,
+            input_log: InputLog::new(false, DEFAULT_INPUT_LOG_LENGTH),
+            show_press_counts: false,
+            stats_since: Duration::ZERO,
+            gambatte_sync: false,
+            input_device: None,
+            gambatte_config: None,
+            layout_path: None,
+            skin_path: None,
+            scale: DEFAULT_SCALE,
+        };
+
+        for (i, key) in layout.keys.iter().enumerate() {
+            app.keys.push(Key { primary_ipt: KeyCode::None, secondary_ipt: KeyCode::None, name: key.name.clone(), reg_entry: key.gambatte_entry.clone(), x: key.x, y: key.y, count: key.count, idx: key.sprite, stats: KeyStats::default() });
+
+            if let Some(dpad) = &key.dpad {
+                let [x_src, y_src, x_dest, y_dest, width, height] = dpad.overhang;
+                let [arrow_idx, arrow_x, arrow_y] = dpad.arrow;
+                app.dpad.push(DpadKey::new(i, arrow_idx, dpad.pressed_x_shift, dpad.pressed_y_shift, x_src, y_src, x_dest, y_dest, width, height, arrow_x, arrow_y));
+            }
+        }
+
+        return app;
+    }
+}
+
+// Layout sizes are in keys, the window is sized in whole pixels of the skin's key sprites. The history panel goes to the right.
+fn window_size(layout: &Layout, skin: &Skin, history: &InputHistory) -> (i32, i32) {
+    let names: Vec<&str> = layout.keys.iter().map(|key| key.name.as_str()).collect();
+    let (panel_width, panel_height) = history.panel_size(&skin.font, &names);
+    let width = (layout.width * skin.keyset.sprite_width as f32).ceil() as i32 + panel_width;
+    let height = ((layout.height * skin.keyset.sprite_height as f32).ceil() as i32).max(panel_height);
+    return (width, height);
 }
 
-pub static mut APP_POINTER: *mut Application = std::ptr::null_mut();
-
-pub fn start() {
-    let mut app = Application {
-        platform: Platform::new(WIDTH, HEIGHT, SCALE, TITLE).unwrap(),
-        keyset: SpriteSheet::new(bmp_load(include_bytes!("gfx/keys.bmp")).unwrap(), KEY_SIZE, KEY_SIZE),
-        arrowset: SpriteSheet::new(bmp_load(include_bytes!("gfx/arrows.bmp")).unwrap(), ARROW_SIZE, ARROW_SIZE),
-        font: SpriteSheet::new(bmp_load(include_bytes!("gfx/font.bmp")).unwrap(), CHARACTER_SIZE, CHARACTER_SIZE),
-        palettes: vec![
-            (String::from("Brown"), vec![[0, 0, 0], [228, 150, 133], [228, 150, 133], [248, 248, 248]]),
-            (String::from("Pastel Mix"), vec![[0, 0, 0], [228, 144, 163], [228, 144, 163], [242, 226, 187]]),
-            (String::from("Blue"), vec![[0, 0, 0], [225, 128, 150], [113, 182, 208], [248, 248, 248]]),
-            (String::from("Green"), vec![[0, 0, 0], [96, 186, 46], [96, 186, 46], [248, 248, 248]]),
-            (String::from("Red"), vec![[0, 0, 0], [131, 198, 86], [225, 128, 150], [248, 248, 248]]),
-            (String::from("Orange"), vec![[0, 0, 0], [232, 186, 77], [232, 186, 77], [248, 248, 248]]),
-            (String::from("Dark Blue"), vec![[0, 0, 0], [225, 128, 150], [141, 156, 191], [248, 248, 248]]),
-            (String::from("Dark Green"), vec![[0, 0, 0], [225, 128, 150], [131, 198, 86], [248, 248, 248]]),
-            (String::from("Dark Brown"), vec![[78, 38, 28], [228, 150, 133], [189, 146, 144], [241, 216, 206]]),
-            (String::from("Yellow"), vec![[0, 0, 0], [113, 182, 208], [232, 186, 77], [248, 248, 248]]),
-            (String::from("Monochrome"), vec![[0, 0, 0], [160, 160, 160], [160, 160, 160], [248, 248, 248]]),
-            (String::from("Inverted"), vec![[248, 248, 248], [24, 128, 104], [24, 128, 104], [0, 0, 0]]),
-        ],
-        palette: Vec::new(),
-        palette_index: 0,
-        keys: vec![
-            Key { primary_ipt: 0, secondary_ipt: 0, name: String::from("UP"), reg_entry: String::from("GameUpKey"), x: 2.0, y: 2.0, idx: 5 },
-            Key { primary_ipt: 0, secondary_ipt: 0, name: String::from("DOWN"), reg_entry: String::from("GameDownKey"), x: 2.0, y: 4.0, idx: 6 },
-            Key { primary_ipt: 0, secondary_ipt: 0, name: String::from("LEFT"), reg_entry: String::from("GameLeftKey"), x: 1.0, y: 3.0, idx: 7 },
-            Key { primary_ipt: 0, secondary_ipt: 0, name: String::from("RIGHT"), reg_entry: String::from("GameRightKey"), x: 3.0, y: 3.0, idx: 8 },
-            Key { primary_ipt: 0, secondary_ipt: 0, name: String::from("SELECT"), reg_entry: String::from("GameSelectKey"), x: 3.5, y: 6.0, idx: 2 },
-            Key { primary_ipt: 0, secondary_ipt: 0, name: String::from("START"), reg_entry: String::from("GameStartKey"), x: 4.5, y: 6.0, idx: 3 },
-            Key { primary_ipt: 0, secondary_ipt: 0, name: String::from("B"), reg_entry: String::from("GameBKey"), x: 5.5, y: 4.0, idx: 1 },
-            Key { primary_ipt: 0, secondary_ipt: 0, name: String::from("A"), reg_entry: String::from("GameAKey"), x: 7.0, y: 3.0, idx: 0 },
-            Key { primary_ipt: 0, secondary_ipt: 0, name: String::from("POWER"), reg_entry: String::from("PlayHard resetKey"), x: 7.0, y: 1.0 - (6.0 / KEY_SIZE as f32), idx: 4 },
-        ],
-        dpad: vec![
-            // TODO: Is there a better way to construct overhangs?
-            DpadKey::new(UP, 0, -2, 2, KEY_SIZE - 2, 2, KEY_SIZE, KEY_SIZE - 4, 2, 10, 8),
-            DpadKey::new(DOWN, 0, 2, 2, 0, 2, -2, KEY_SIZE - 4, 2, 10, 6),
-            DpadKey::new(LEFT, -2, 0, KEY_SIZE - 2, 2, KEY_SIZE, 2, 2, KEY_SIZE - 4, 8, 7),
-            DpadKey::new(RIGHT, 2, 0, 0, 2, -2, 2, 2, KEY_SIZE - 4, 12, 7),
-        ],
-        key_to_configure: -1,
-        text_buffer: String::from(""),
-        gambatte_sync: false,
+pub fn start(input_device: Option<&str>) {
+    let configuration = read_configuration();
+
+    // A device given on the command line wins over the configured one, but is not saved.
+    let configured_device = configuration.as_ref().ok().and_then(|configuration| configuration.input_device.clone());
+    let layout_path = configuration.as_ref().ok().and_then(|configuration| configuration.layout_path.clone());
+    let skin_path = configuration.as_ref().ok().and_then(|configuration| configuration.skin_path.clone());
+    let scale = configuration.as_ref().map_or(DEFAULT_SCALE, |configuration| configuration.scale);
+    let history = match &configuration {
+        Ok(configuration) => InputHistory::new(configuration.history, configuration.history_length, configuration.history_speed),
+        Err(_) => InputHistory::new(false, DEFAULT_HISTORY_LENGTH, DEFAULT_HISTORY_SPEED),
     };
+    let layout = Layout::load(layout_path.as_deref());
+    let skin = Skin::load(skin_path.as_deref());
+
+    let (width, height) = window_size(&layout, &skin, &history);
+    let platform = create_platform(width, height, scale, TITLE, input_device.or(configured_device.as_deref())).unwrap();
+    let mut app = Application::new(platform, &layout, skin, history);
+    app.scale = scale;
+
+    match configuration {
+        Ok(configuration) => load_configuration(&mut app, &configuration),
+        Err(ref error) if error.kind() == std::io::ErrorKind::NotFound => match migrate_configuration(&app) {
+            Some(configuration) => {
+                load_configuration(&mut app, &configuration);
+                save_configuration(&app).ok();
+            }
+            None => select_palette(&mut app, DEFAULT_PALETTE),
+        },
+        Err(_) => select_palette(&mut app, DEFAULT_PALETTE),
+    }
+
+    run(&mut app);
+
+    if let Err(error) = export_stats(&app) {
+        eprintln!("Could not export the session statistics: {}", error);
+    }
+}
+
+// Runs a script through the regular key handling without a window and returns every frame that would have been presented.
+// The user's configuration is never touched, so the result only depends on the script.
+pub fn render_headless(script: Script) -> Vec<BackBuffer> {
+    let layout = Layout::load(script.layout.as_deref());
+    let skin = Skin::load(script.skin.as_deref());
+    let history = match script.history {
+        Some((length, speed)) => InputHistory::new(true, length, speed),
+        None => InputHistory::new(false, DEFAULT_HISTORY_LENGTH, DEFAULT_HISTORY_SPEED),
+    };
+    let mut app = Application::new(Box::new(HeadlessPlatform::new(script.steps)), &layout, skin, history);
+    app.offscreen_buffer.transparent_background = script.transparent_background;
+    app.scale = script.scale.filter(|scale| (1..=MAX_SCALE).contains(scale)).unwrap_or(DEFAULT_SCALE);
+    app.platform.set_scale(app.scale).unwrap();
+    if let Some(length) = script.input_log {
+        app.input_log = InputLog::new(true, length);
+    }
+    app.show_press_counts = script.press_counts;
+
+    for (name, primary, secondary) in script.bindings.iter() {
+        if let Some(key) = app.keys.iter_mut().find(|key| &key.name == name) {
+            key.primary_ipt = *primary;
+            key.secondary_ipt = *secondary;
+        }
+    }
 
-    app.platform.register_callback(WM_CLOSE, on_quit);
-    app.platform.register_callback(WM_RBUTTONUP, on_rightclick);
-    app.platform.register_hook(WH_KEYBOARD_LL, on_key_state);
+    let palette = script.palette.as_deref().unwrap_or(DEFAULT_PALETTE);
+    match palette.parse::<usize>() {
+        Ok(index) if index < app.palettes.len() => change_palette(&mut app, index),
+        _ => select_palette(&mut app, palette),
+    }
+    run(&mut app);
 
-    unsafe { APP_POINTER = &mut app };
+    return app.platform.take_frames();
+}
 
-    match load_configuration() {
-        Ok(_) => {}
-        Err(_) => change_palette(3),
+// Every change to the application happens here, one event at a time.
+pub fn run(app: &mut Application) {
+    loop {
+        match app.platform.next_event() {
+            Event::KeyChanged(key_code, key_state) => on_key_state(app, key_code, key_state),
+            Event::MenuCommand(id) => on_menu_command(app, id),
+            Event::Timer => on_timer(app),
+            Event::Quit => break,
+        }
     }
+}
 
-    app.platform.start_message_queue();
+pub fn notify(app: &mut Application, notification: Notification) {
+    if app.notifications.post(notification) {
+        show_notification(app);
+    }
 }
 
-fn on_quit(_wparam: usize, _lparam: usize) {
-    let mut app = unsafe { &mut *APP_POINTER };
-    app.platform.running = false;
+pub fn dismiss_notification(app: &mut Application, source: NotificationSource) {
+    if app.notifications.dismiss(source) {
+        show_notification(app);
+    }
 }
 
-fn on_rightclick(_wparam: usize, _lparam: usize) {
-    let app = unsafe { &mut *APP_POINTER };
-    let mut item_counter = 0;
+fn show_notification(app: &mut Application) {
+    let duration = app.notifications.current().and_then(|notification| notification.duration);
+    app.notification_deadline = duration.map(|duration| app.platform.time() + duration);
+    draw_background(app);
+    arm_timer(app);
+}
+
+// The platform has a single timer, shared by the notification on screen, the scrolling history panel and the hold counts of the input log.
+pub fn arm_timer(app: &mut Application) {
+    let now = app.platform.time();
+    let scroll = if app.history.is_scrolling() { Some(now + app.history.scroll_interval()) } else { None };
+    let count = if app.input_log.is_counting() { Some(now + frame_duration()) } else { None };
+    let deadline = app.notification_deadline.into_iter().chain(scroll).chain(count).min();
+    app.platform.set_timer(deadline.map(|deadline| deadline.saturating_sub(now)));
+}
+
+fn on_timer(app: &mut Application) {
+    if app.notification_deadline.is_some_and(|deadline| deadline <= app.platform.time()) {
+        app.notification_deadline = None;
+        if app.notifications.expire() {
+            show_notification(app);
+            return;
+        }
+    }
+
+    if app.history.is_scrolling() || app.input_log.is_counting() {
+        draw_background(app);
+    }
+    arm_timer(app);
+}
 
+pub fn update_menu(app: &mut Application) {
     let mut palette_menu: Vec<MenuItem> = Vec::new();
 
     for (i, pal) in app.palettes.iter().enumerate() {
-        palette_menu.push(if app.palette_index == i { MenuItem::Checked(pal.0.clone()) } else { MenuItem::Unchecked(pal.0.clone()) });
-    }
-
-    let res = app.platform.show_menu(
-        &[
-            MenuItem::Unchecked(String::from("Sync Gambatte Keybinds")),
-            MenuItem::Unchecked(String::from("Set Keybinds")),
-            MenuItem::Seperator,
-            MenuItem::SubMenu(String::from("Palettes"), app.platform.create_menu(palette_menu.as_slice(), &mut item_counter)),
-        ],
-        &mut item_counter,
-    ) as usize;
-
-    if res == palette_menu.len() + 1 {
-        let result_text = match sync_gambatte_keybindings() {
-            Ok(_) => "SUCCESS",
-            Err(_) => "FAILURE",
-        };
-        thread::spawn(move || {
-            app.text_buffer = String::from(result_text);
-            draw_background();
-            app.text_buffer = String::from("");
-            thread::sleep(Duration::from_secs(3));
-            draw_background();
-        });
-    } else if res == palette_menu.len() + 2 {
-        configure_next_key();
-    } else if res > 0 && res <= palette_menu.len() {
-        change_palette(res as usize - 1)
+        let id = MENU_PALETTE + i as u32;
+        palette_menu.push(if app.palette_index == i { MenuItem::Checked(pal.0.clone(), id) } else { MenuItem::Unchecked(pal.0.clone(), id) });
+    }
+
+    palette_menu.push(MenuItem::Seperator);
+    palette_menu.push(MenuItem::Unchecked(String::from("Add Palette"), MENU_ADD_PALETTE));
+    if !is_built_in(app.palette_index) {
+        palette_menu.push(MenuItem::Unchecked(String::from("Rename Palette"), MENU_RENAME_PALETTE));
+        palette_menu.push(MenuItem::Unchecked(String::from("Delete Palette"), MENU_DELETE_PALETTE));
+    }
+    palette_menu.push(MenuItem::Seperator);
+    palette_menu.push(MenuItem::Unchecked(String::from("Import Palettes"), MENU_IMPORT_PALETTES));
+    palette_menu.push(MenuItem::Unchecked(String::from("Export Palettes"), MENU_EXPORT_PALETTES));
+
+    let mut scale_menu: Vec<MenuItem> = Vec::new();
+
+    for scale in 1..=MAX_SCALE {
+        let id = MENU_SCALE + scale as u32;
+        let name = format!("{}x", scale);
+        scale_menu.push(if app.scale == scale { MenuItem::Checked(name, id) } else { MenuItem::Unchecked(name, id) });
+    }
+
+    app.platform.set_menu(vec![
+        MenuItem::Unchecked(String::from("Sync Gambatte Keybinds"), MENU_SYNC_GAMBATTE),
+        MenuItem::Unchecked(String::from("Set Keybinds"), MENU_SET_KEYBINDS),
+        if app.input_log.visible { MenuItem::Checked(String::from("Input Log"), MENU_INPUT_LOG) } else { MenuItem::Unchecked(String::from("Input Log"), MENU_INPUT_LOG) },
+        if app.show_press_counts { MenuItem::Checked(String::from("Press Counts"), MENU_PRESS_COUNTS) } else { MenuItem::Unchecked(String::from("Press Counts"), MENU_PRESS_COUNTS) },
+        MenuItem::Unchecked(String::from("Reset Stats"), MENU_RESET_STATS),
+        MenuItem::Seperator,
+        MenuItem::SubMenu(String::from("Palettes"), palette_menu),
+        MenuItem::SubMenu(String::from("Scale"), scale_menu),
+    ]);
+}
+
+fn on_menu_command(app: &mut Application, id: u32) {
+    match id {
+        MENU_SYNC_GAMBATTE => {
+            let text = match sync_gambatte_keybindings(app) {
+                Ok(_) => "SUCCESS",
+                Err(_) => "FAILURE",
+            };
+            notify(app, Notification::new(text, Priority::Normal, Some(SYNC_MESSAGE_DURATION), NotificationSource::GambatteSync));
+        }
+        MENU_SET_KEYBINDS => configure_next_key(app),
+        MENU_INPUT_LOG => toggle_input_log(app),
+        MENU_PRESS_COUNTS => toggle_press_counts(app),
+        MENU_RESET_STATS => reset_stats(app),
+        MENU_ADD_PALETTE => add_palette(app),
+        MENU_RENAME_PALETTE => start_renaming_palette(app),
+        MENU_DELETE_PALETTE => delete_palette(app),
+        MENU_IMPORT_PALETTES => {
+            let text = match import_palettes(app) {
+                Ok(_) => "IMPORTED",
+                Err(_) => "FAILURE",
+            };
+            notify_palettes(app, text);
+        }
+        MENU_EXPORT_PALETTES => {
+            let text = match export_palettes(app) {
+                Ok(_) => "EXPORTED",
+                Err(_) => "FAILURE",
+            };
+            notify_palettes(app, text);
+        }
+        id if id > MENU_SCALE && id <= MENU_SCALE + MAX_SCALE as u32 => change_scale(app, (id - MENU_SCALE) as i32),
+        id if id >= MENU_PALETTE => change_palette(app, (id - MENU_PALETTE) as usize),
+        _ => {}
     }
 
-    save_configuration().ok();
+    save_configuration(app).ok();
 }
 
-pub fn draw_background() {
-    let app = unsafe { &mut *APP_POINTER };
+pub fn draw_background(app: &mut Application) {
+    let key_width = app.keyset.sprite_width;
+    let key_height = app.keyset.sprite_height;
 
     macro_rules! coord {
-        ($base:expr, $offs:expr) => {
-            ($base * KEY_SIZE as f32) as i32 + $offs
+        ($base:expr, $size:expr, $offs:expr) => {
+            ($base * $size as f32) as i32 + $offs
         };
     }
 
     macro_rules! draw {
         ($sheet:expr, $x:expr, $y:expr, $idx:expr) => {
-            app.platform.offscreen_buffer.draw_sprite(&app.palette, $sheet, coord!($x, 0), coord!($y, 0) as i32, $idx)
+            app.offscreen_buffer.draw_sprite(&app.palette, $sheet, coord!($x, key_width, 0), coord!($y, key_height, 0) as i32, $idx)
         };
     }
 
-    update_dpad();
+    update_dpad(app);
 
-    app.platform.offscreen_buffer.clear(app.palette[4]);
+    app.offscreen_buffer.clear(app.palette[BACKGROUND_INDEX]);
     for key in app.keys.iter() {
         draw!(&app.keyset, key.x, key.y, key.idx);
     }
 
-    draw!(&app.keyset, app.keys[UP].x, app.keys[UP].y + 1.0, 32);
+    if let Some((x, y, idx)) = app.dpad_center {
+        draw!(&app.keyset, x, y, idx);
+    }
 
     for dpad in app.dpad.iter() {
         let key = &app.keys[dpad.key_id];
         draw!(
             &app.arrowset,
-            key.x + (dpad.arrow.key_x_offset + dpad.arrow.current_x_shift) as f32 / KEY_SIZE as f32,
-            key.y + (dpad.arrow.key_y_offset + dpad.arrow.current_y_shift) as f32 / KEY_SIZE as f32,
+            key.x + (dpad.arrow.key_x_offset + dpad.arrow.current_x_shift) as f32 / key_width as f32,
+            key.y + (dpad.arrow.key_y_offset + dpad.arrow.current_y_shift) as f32 / key_height as f32,
             dpad.arrow.idx
         );
 
-        app.platform.offscreen_buffer.draw_subsprite(
+        app.offscreen_buffer.draw_subsprite(
             &app.palette,
             &app.keyset,
-            coord!(key.x, dpad.overhang.x_dest),
-            coord!(key.y, dpad.overhang.y_dest),
+            coord!(key.x, key_width, dpad.overhang.x_dest),
+            coord!(key.y, key_height, dpad.overhang.y_dest),
             key.idx,
             dpad.overhang.x_src,
             dpad.overhang.y_src,
@@ -193,51 +374,189 @@ pub fn draw_background() {
         );
     }
 
-    app.platform.offscreen_buffer.draw_text(&app.palette, &app.font, &app.text_buffer, (WIDTH - app.text_buffer.len() as i32 * CHARACTER_SIZE) / 2, coord!(7.0, (KEY_SIZE - CHARACTER_SIZE) / 2));
+    // Only keys the layout gives a place for their count show one, centred in a key wide box.
+    if app.show_press_counts {
+        for key in app.keys.iter() {
+            if let Some((count_x, count_y)) = key.count {
+                let text = key.stats.presses.to_string();
+                let x = coord!(count_x, key_width, (key_width - app.font.text_width(&text)) / 2);
+                app.offscreen_buffer.draw_text(&app.palette, &app.font, &text, x, coord!(count_y, key_height, 0));
+            }
+        }
+    }
+
+    draw_history(app);
 
-    app.platform.update_window();
+    // The input log takes the text line whenever it is free.
+    let width = app.offscreen_buffer.width - history_panel_width(app);
+    let text = if app.input_log.visible && app.notifications.current().is_none() { input_log_text(app, width) } else { app.notifications.text().to_string() };
+    let x = (width - app.font.text_width(&text)) / 2;
+    let y = coord!(app.text_row, key_height, (key_height - app.font.sheet.sprite_height) / 2);
+    app.offscreen_buffer.draw_text(&app.palette, &app.font, &text, x, y);
+
+    app.platform.update_window(&app.offscreen_buffer);
 }
 
-pub fn change_palette(index: usize) {
-    let app = unsafe { &mut *APP_POINTER };
+pub fn change_palette(app: &mut Application, index: usize) {
     if index >= app.palettes.len() {
         return;
     }
 
+    // A rename in progress belongs to the palette that was shown before.
+    if app.palette_name.take().is_some() {
+        dismiss_notification(app, NotificationSource::Palettes);
+    }
+
     let mut new_palette = app.palettes[index].1.clone();
 
     // 2 palette entries are inserted:
     //   The first one is reserved for the future
     //   The second one is the background color + 1 green for chromakey purposes
     new_palette.insert(3, [0, 0, 0]);
-    new_palette.insert(4, [new_palette[4][0], new_palette[4][1] + 1, new_palette[4][2]]);
+    new_palette.insert(BACKGROUND_INDEX, [new_palette[4][0], new_palette[4][1].checked_add(1).unwrap_or(254), new_palette[4][2]]);
 
     app.palette = new_palette;
     app.palette_index = index;
-    draw_background();
+    update_menu(app);
+    draw_background(app);
+}
+
+// Only whole factors are offered, so every pixel stays a sharp square.
+pub fn change_scale(app: &mut Application, scale: i32) {
+    if !(1..=MAX_SCALE).contains(&scale) || scale == app.scale {
+        return;
+    }
+
+    // The scale and its menu check only change once the window has taken the new size.
+    if let Err(error) = app.platform.set_scale(scale) {
+        eprintln!("Could not change the scale to {}: {:?}", scale, error);
+        return;
+    }
+
+    app.scale = scale;
+    update_menu(app);
+    draw_background(app);
 }
 
-unsafe extern "system" fn on_key_state(code: i32, wparam: usize, lparam: usize) -> usize {
-    let app = &mut *APP_POINTER;
-    let key_code = std::ptr::read(lparam as *const u32);
-    let key_state = app.platform.key_state_from_wparam(wparam);
+fn on_key_state(app: &mut Application, key_code: KeyCode, key_state: KeyState) {
+    // None is what unbound keys are set to, it must not match them.
+    if key_code == KeyCode::None {
+        return;
+    }
 
-    if app.key_to_configure == -1 {
-        let mut key = app.keys.iter_mut().find(|key| key.primary_ipt == key_code || key.secondary_ipt == key_code);
+    if app.palette_name.is_some() {
+        if key_state == KeyState::Pressed {
+            edit_palette_name(app, key_code);
+        }
+    } else if app.key_to_configure == -1 {
+        let mut key = app.keys.iter().position(|key| key.primary_ipt == key_code || key.secondary_ipt == key_code);
 
-        if let None = key {
-            if (app.platform.is_key_down(VK_CONTROL) && key_code as u32 == 'R' as u32) || (app.platform.is_key_down('R' as u32) && key_code == VK_CONTROL) {
-                key = Some(&mut app.keys[POWER]);
-            }
+        if key.is_none() && ((app.platform.is_key_down(KeyCode::LControl) && key_code == KeyCode::R) || (app.platform.is_key_down(KeyCode::R) && key_code == KeyCode::LControl)) {
+            key = app.keys.iter().position(|key| key.name == POWER);
+        }
+
+        // Control + Backspace resets the statistics, unless Backspace is bound to a key.
+        if key.is_none() && key_state == KeyState::Pressed && key_code == KeyCode::Backspace && app.platform.is_key_down(KeyCode::LControl) {
+            reset_stats(app);
+            return;
         }
 
         if let Some(key) = key {
-            key.set_pressed(key_state == KeyState::Pressed);
-            draw_background();
+            app.keys[key].set_pressed(key_state == KeyState::Pressed);
+            record_key_state(app, key, key_state == KeyState::Pressed);
+            count_key_state(app, key, key_state == KeyState::Pressed);
+            log_key_state(app, key, key_state == KeyState::Pressed);
+            draw_background(app);
+            arm_timer(app);
         }
     } else if key_state == KeyState::Pressed {
-        configure_current_key(key_code);
+        configure_current_key(app, key_code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(script: &str) -> Vec<BackBuffer> {
+        return render_headless(Script::parse(script).unwrap());
+    }
+
+    // Each press and release presents one frame, in which only the pressed key changes.
+    #[test]
+    fn pressing_a_key_draws_its_pressed_sprite() {
+        let frames = render("bind A 0x5A\npress 0x5A\nrelease 0x5A\n");
+        assert_eq!(frames.len(), 3);
+
+        // A sits at 7, 3 of the default layout.
+        let (x, y) = (7 * KEY_SIZE, 3 * KEY_SIZE);
+        let width = frames[0].width;
+        let inside = |pixel: usize| {
+            let (px, py) = ((pixel as i32) % width, (pixel as i32) / width);
+            return px >= x && px < x + KEY_SIZE && py >= y && py < y + KEY_SIZE;
+        };
+        let changed: Vec<usize> = (0..(width * frames[0].height) as usize).filter(|pixel| frames[0].memory[pixel * 4..pixel * 4 + 4] != frames[1].memory[pixel * 4..pixel * 4 + 4]).collect();
+        assert!(!changed.is_empty());
+        assert!(changed.iter().all(|pixel| inside(*pixel)));
+        assert_eq!(frames[2].memory, frames[0].memory);
+    }
+
+    // Keys no binding matches, including unbound ones, present nothing.
+    #[test]
+    fn unbound_keys_draw_nothing() {
+        assert_eq!(render("press 0x5A\nrelease 0x5A\n").len(), 1);
+        assert_eq!(render("press 0\nrelease 0\n").len(), 1);
+    }
+
+    // Takes scales up to 2 and remembers which scale the last menu had checked.
+    struct SmallWindow {
+        checked_scale: std::rc::Rc<std::cell::Cell<Option<u32>>>,
+    }
+
+    impl Platform for SmallWindow {
+        fn next_event(&mut self) -> Event {
+            return Event::Quit;
+        }
+
+        fn set_menu(&mut self, items: Vec<MenuItem>) {
+            for item in items.iter() {
+                if let MenuItem::SubMenu(name, entries) = item {
+                    if name == "Scale" {
+                        self.checked_scale.set(entries.iter().find_map(|entry| if let MenuItem::Checked(_, id) = entry { Some(id - MENU_SCALE) } else { None }));
+                    }
+                }
+            }
+        }
+
+        fn set_timer(&mut self, _timeout: Option<Duration>) {}
+
+        fn time(&self) -> Duration {
+            return Duration::ZERO;
+        }
+
+        fn update_window(&mut self, _buffer: &BackBuffer) {}
+
+        fn set_scale(&mut self, scale: i32) -> Result<(), PlatfromError> {
+            return if scale <= 2 { Ok(()) } else { Err(PlatfromError::Unsupported) };
+        }
+
+        fn is_key_down(&self, _code: KeyCode) -> bool {
+            return false;
+        }
     }
 
-    return CallNextHookEx(0, code, wparam, lparam);
+    #[test]
+    fn refused_scales_change_nothing() {
+        let checked_scale = std::rc::Rc::new(std::cell::Cell::new(None));
+        let platform = SmallWindow { checked_scale: checked_scale.clone() };
+        let mut app = Application::new(Box::new(platform), &Layout::default_layout(), Skin::load(None), InputHistory::new(false, DEFAULT_HISTORY_LENGTH, DEFAULT_HISTORY_SPEED));
+        select_palette(&mut app, DEFAULT_PALETTE);
+        app.scale = 1;
+
+        on_menu_command(&mut app, MENU_SCALE + 2);
+        assert_eq!((app.scale, checked_scale.get()), (2, Some(2)));
+
+        on_menu_command(&mut app, MENU_SCALE + 3);
+        assert_eq!((app.scale, checked_scale.get()), (2, Some(2)));
+    }
 }
@@ -2,22 +2,46 @@ extern crate byteorder;
 
 use byteorder::*;
 use std::convert::*;
-use std::io::{Cursor, Error, Read, Seek, SeekFrom};
+use std::fmt;
+use std::io::{Error, Write};
 use std::ops::Index;
+use std::path::*;
 
+pub type Rgba = [u8; 4];
+
+// Every pixel is reduced to a palette index in data. True colour bitmaps also keep the decoded pixels, which are drawn as they are.
 pub struct Bitmap {
     pub width: i32,
     pub height: i32,
     pub data: Vec<u8>,
+    pub rgba: Option<Vec<Rgba>>,
 }
 
-#[derive(Debug)]
-pub enum BMPError {
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BMPErrorKind {
     WrongSignature,
+    UnsupportedHeader,
     UnsupportedBitsPerPixel,
+    UnsupportedCompression,
+    InvalidDimensions,
+    InvalidFileSize,
+    InvalidBitmapOffset,
+    Truncated,
     IOError,
 }
 
+// What went wrong and where. The offset is the position in the file of the value that was rejected,
+// expected and found describe the value, e.g. "24 bytes" and "10 bytes" for a truncated header.
+#[derive(Debug)]
+pub struct BMPError {
+    pub kind: BMPErrorKind,
+    pub file_name: Option<String>,
+    pub offset: Option<usize>,
+    pub expected: String,
+    pub found: String,
+}
+
+#[allow(dead_code)]
 struct BitmapHeader {
     pub file_size: u32,
     pub reserved: u32,
@@ -27,11 +51,57 @@ struct BitmapHeader {
     pub height: i32,
     pub planes: u16,
     pub bits_per_pixel: u16,
+    pub compression: u32,
+    pub colors_used: u32,
+}
+
+impl BMPError {
+    fn new(kind: BMPErrorKind, offset: usize, expected: &str, found: &str) -> BMPError {
+        return BMPError { kind: kind, file_name: None, offset: Some(offset), expected: expected.to_string(), found: found.to_string() };
+    }
+
+    pub fn with_file_name(mut self, file_name: &str) -> BMPError {
+        self.file_name = Some(file_name.to_string());
+        return self;
+    }
 }
 
 impl From<Error> for BMPError {
-    fn from(_error: Error) -> Self {
-        return BMPError::IOError;
+    fn from(error: Error) -> Self {
+        return BMPError { kind: BMPErrorKind::IOError, file_name: None, offset: None, expected: String::new(), found: error.to_string() };
+    }
+}
+
+impl fmt::Display for BMPError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file_name) = &self.file_name {
+            write!(f, "{}: ", file_name)?;
+        }
+
+        let description = match self.kind {
+            BMPErrorKind::WrongSignature => "not a bitmap",
+            BMPErrorKind::UnsupportedHeader => "unsupported header",
+            BMPErrorKind::UnsupportedBitsPerPixel => "unsupported bits per pixel",
+            BMPErrorKind::UnsupportedCompression => "unsupported compression",
+            BMPErrorKind::InvalidDimensions => "invalid dimensions",
+            BMPErrorKind::InvalidFileSize => "invalid file size",
+            BMPErrorKind::InvalidBitmapOffset => "invalid pixel data offset",
+            BMPErrorKind::Truncated => "file is truncated",
+            BMPErrorKind::IOError => "I/O error",
+        };
+        write!(f, "{}", description)?;
+
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+
+        if !self.expected.is_empty() {
+            write!(f, ": expected {}, found {}", self.expected, self.found)?;
+        } else if !self.found.is_empty() {
+            write!(f, ": {}", self.found)?;
+        }
+
+        return Ok(());
     }
 }
 
@@ -44,40 +114,505 @@ impl Index<usize> for Bitmap {
 }
 
 const BMP_SIGNATURE: u16 = 0x424d;
+const BMP_HEADER_SIZE: u32 = 14;
+const BMP_CORE_HEADER_SIZE: u32 = 12;
+const BMP_INFO_HEADER_SIZE: u32 = 40;
+const BMP_V2_HEADER_SIZE: u32 = 52;
+const BMP_V3_HEADER_SIZE: u32 = 56;
+const BMP_V4_HEADER_SIZE: u32 = 108;
+const BMP_PIXELS_PER_METER: i32 = 3780;
 const NUM_COLORS: u8 = 5;
 
-pub fn bmp_load(data: &[u8]) -> Result<Bitmap, BMPError> {
-    let mut pointer = Cursor::new(data);
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+const LCS_SRGB: u32 = 0x73524742;
+
+// Red, green, blue and alpha masks. An alpha mask of 0 means the pixels are opaque.
+type Masks = [u32; 4];
+
+const DEFAULT_MASKS: Masks = [0x00ff0000, 0x0000ff00, 0x000000ff, 0];
+
+// Bounds checked little-endian reads, a short file is reported instead of read past its end.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], BMPError> {
+        match self.data.get(self.offset..self.offset.saturating_add(count)) {
+            Some(bytes) => {
+                self.offset += count;
+                return Ok(bytes);
+            }
+            None => {
+                let available = self.data.len().saturating_sub(self.offset);
+                return Err(BMPError::new(BMPErrorKind::Truncated, self.offset, &format!("{} bytes", count), &format!("{} bytes", available)));
+            }
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, BMPError> {
+        let bytes = self.bytes(2)?;
+        return Ok(u16::from_le_bytes([bytes[0], bytes[1]]));
+    }
+
+    fn u32(&mut self) -> Result<u32, BMPError> {
+        let bytes = self.bytes(4)?;
+        return Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    }
+
+    fn i32(&mut self) -> Result<i32, BMPError> {
+        return Ok(self.u32()? as i32);
+    }
+}
+
+// Reads the file and info headers. OS/2 core headers only have 16 bit dimensions and no compression.
+fn read_header(reader: &mut Reader) -> Result<BitmapHeader, BMPError> {
+    let signature = reader.u16()?.swap_bytes();
+    if signature != BMP_SIGNATURE {
+        return Err(BMPError::new(BMPErrorKind::WrongSignature, 0, "\"BM\"", &format!("{:#06x}", signature)));
+    }
 
-    if pointer.read_u16::<BigEndian>()? != BMP_SIGNATURE {
-        return Err(BMPError::WrongSignature);
+    let file_size = reader.u32()?;
+    let reserved = reader.u32()?;
+    let bitmap_offset = reader.u32()?;
+    let size = reader.u32()?;
+
+    if size == BMP_CORE_HEADER_SIZE {
+        return Ok(BitmapHeader {
+            file_size: file_size,
+            reserved: reserved,
+            bitmap_offset: bitmap_offset,
+            size: size,
+            width: reader.u16()? as i32,
+            height: reader.u16()? as i32,
+            planes: reader.u16()?,
+            bits_per_pixel: reader.u16()?,
+            compression: BI_RGB,
+            colors_used: 0,
+        });
+    }
+
+    if size < BMP_INFO_HEADER_SIZE {
+        return Err(BMPError::new(BMPErrorKind::UnsupportedHeader, reader.offset - 4, &format!("{} or at least {} bytes", BMP_CORE_HEADER_SIZE, BMP_INFO_HEADER_SIZE), &format!("{} bytes", size)));
     }
 
-    let header = BitmapHeader {
-        file_size: pointer.read_u32::<LittleEndian>()?,
-        reserved: pointer.read_u32::<LittleEndian>()?,
-        bitmap_offset: pointer.read_u32::<LittleEndian>()?,
-        size: pointer.read_u32::<LittleEndian>()?,
-        width: pointer.read_i32::<LittleEndian>()?,
-        height: pointer.read_i32::<LittleEndian>()?,
-        planes: pointer.read_u16::<LittleEndian>()?,
-        bits_per_pixel: pointer.read_u16::<LittleEndian>()?,
+    let width = reader.i32()?;
+    let height = reader.i32()?;
+    let planes = reader.u16()?;
+    let bits_per_pixel = reader.u16()?;
+    let compression = reader.u32()?;
+    reader.bytes(12)?; // image size and resolution
+    let colors_used = reader.u32()?;
+
+    return Ok(BitmapHeader {
+        file_size: file_size,
+        reserved: reserved,
+        bitmap_offset: bitmap_offset,
+        size: size,
+        width: width,
+        height: height,
+        planes: planes,
+        bits_per_pixel: bits_per_pixel,
+        compression: compression,
+        colors_used: colors_used,
+    });
+}
+
+// BITFIELDS masks follow a plain info header, newer headers have room for them inside. Returns the masks and where the color table starts.
+// A header that claims to be longer than the whole file is rejected here, before any offset is worked out from its size.
+fn read_masks(reader: &mut Reader, header: &BitmapHeader) -> Result<(Masks, usize), BMPError> {
+    let table_offset = match (BMP_HEADER_SIZE as usize).checked_add(header.size as usize) {
+        Some(offset) if offset <= reader.data.len() => offset,
+        _ => return Err(BMPError::new(BMPErrorKind::UnsupportedHeader, BMP_HEADER_SIZE as usize, &format!("at most {} bytes", reader.data.len() - BMP_HEADER_SIZE as usize), &format!("{} bytes", header.size))),
+    };
+
+    let mask_count = match header.compression {
+        BI_RGB => return Ok((DEFAULT_MASKS, table_offset)),
+        BI_BITFIELDS if header.size >= BMP_V3_HEADER_SIZE => 4,
+        BI_BITFIELDS => 3,
+        BI_ALPHABITFIELDS => 4,
+        _ => return Err(BMPError::new(BMPErrorKind::UnsupportedCompression, 30, "BI_RGB or BI_BITFIELDS", &header.compression.to_string())),
     };
 
-    if header.bits_per_pixel != 32 {
-        return Err(BMPError::UnsupportedBitsPerPixel);
+    reader.offset = (BMP_HEADER_SIZE + BMP_INFO_HEADER_SIZE) as usize;
+    let mut masks = [0; 4];
+    for mask in masks.iter_mut().take(mask_count) {
+        *mask = reader.u32()?;
+    }
+
+    if header.size >= BMP_V2_HEADER_SIZE {
+        return Ok((masks, table_offset));
+    }
+
+    return Ok((masks, table_offset + mask_count * 4));
+}
+
+// Core headers store BGR triples, everything else BGRX quads.
+fn read_color_table(reader: &mut Reader, header: &BitmapHeader, offset: usize) -> Result<Vec<Rgba>, BMPError> {
+    let max_colors = 1u32 << header.bits_per_pixel;
+    let count = if header.colors_used == 0 || header.colors_used > max_colors { max_colors } else { header.colors_used };
+    let entry_size = if header.size == BMP_CORE_HEADER_SIZE { 3 } else { 4 };
+
+    reader.offset = offset;
+    let mut colors = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let entry = reader.bytes(entry_size)?;
+        colors.push([entry[2], entry[1], entry[0], 255]);
+    }
+
+    return Ok(colors);
+}
+
+// Scales the bits selected by the mask to 0-255.
+fn channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
     }
 
-    let mut px = [0; 4];
-    let mut data = vec![0; (header.width * header.height) as usize];
+    let max = mask >> mask.trailing_zeros();
+    return ((((value & mask) >> mask.trailing_zeros()) as u64 * 255 + max as u64 / 2) / max as u64) as u8;
+}
+
+// Decodes the pixels to RGBA, top row first. Everything the header claims is checked against the length of the data before it is used.
+fn read_pixels(data: &[u8]) -> Result<(i32, i32, Vec<Rgba>), BMPError> {
+    let mut reader = Reader { data: data, offset: 0 };
+    let header = read_header(&mut reader)?;
+    let core = header.size == BMP_CORE_HEADER_SIZE;
+
+    // Some writers leave the file size at 0, a size past the end of the data means the file was cut short.
+    if header.file_size as usize > data.len() {
+        return Err(BMPError::new(BMPErrorKind::InvalidFileSize, 2, &format!("{} bytes", header.file_size), &format!("{} bytes", data.len())));
+    }
+
+    if header.width <= 0 || header.height == 0 || header.height == i32::MIN {
+        return Err(BMPError::new(BMPErrorKind::InvalidDimensions, 18, "a positive width and a non-zero height", &format!("{}x{}", header.width, header.height)));
+    }
+
+    let bits_per_pixel = header.bits_per_pixel as usize;
+    match bits_per_pixel {
+        1 | 4 | 8 | 24 | 32 => {}
+        _ => return Err(BMPError::new(BMPErrorKind::UnsupportedBitsPerPixel, if core { 24 } else { 28 }, "1, 4, 8, 24 or 32", &bits_per_pixel.to_string())),
+    }
+
+    // Paletted and 24 bpp images cannot be BITFIELDS encoded, RLE is not supported.
+    if header.compression != BI_RGB && bits_per_pixel != 32 {
+        return Err(BMPError::new(BMPErrorKind::UnsupportedCompression, 30, "BI_RGB", &header.compression.to_string()));
+    }
+
+    let (masks, table_offset) = read_masks(&mut reader, &header)?;
+    let colors = if bits_per_pixel <= 8 { read_color_table(&mut reader, &header, table_offset)? } else { Vec::new() };
+
+    // The pixels come after the headers and the color table, and have to fit in what is left of the file.
+    let bitmap_offset = header.bitmap_offset as usize;
+    let headers_end = if bits_per_pixel <= 8 { reader.offset } else { table_offset };
+    if bitmap_offset < headers_end || bitmap_offset > data.len() {
+        return Err(BMPError::new(BMPErrorKind::InvalidBitmapOffset, 10, &format!("{} to {}", headers_end, data.len()), &bitmap_offset.to_string()));
+    }
+
+    // A negative height marks a top-down bitmap, otherwise the last row comes first.
+    let width = header.width;
+    let height = header.height.abs();
+    let bottom_up = header.height > 0;
+
+    // Rows are padded to a multiple of 4 bytes.
+    let stride = (width as usize * bits_per_pixel).div_ceil(32) * 4;
+    let pixel_data_size = stride as u64 * height as u64;
+    if pixel_data_size > (data.len() - bitmap_offset) as u64 {
+        return Err(BMPError::new(BMPErrorKind::Truncated, bitmap_offset, &format!("{} bytes of pixel data", pixel_data_size), &format!("{} bytes", data.len() - bitmap_offset)));
+    }
+
+    let mut pixels = vec![[0; 4]; width as usize * height as usize];
+
+    for (y, row) in data[bitmap_offset..].chunks_exact(stride).take(height as usize).enumerate() {
+        let dest_row = if bottom_up { height as usize - y - 1 } else { y };
+
+        for x in 0..width as usize {
+            let pixel = match bits_per_pixel {
+                1 | 4 | 8 => {
+                    let bit = x * bits_per_pixel;
+                    let index = (row[bit / 8] >> (8 - bits_per_pixel - bit % 8)) & ((1 << bits_per_pixel) - 1) as u8;
+                    // Indices past the end of the color table are drawn black rather than rejecting the file.
+                    colors.get(index as usize).cloned().unwrap_or([0, 0, 0, 255])
+                }
+                24 => [row[x * 3 + 2], row[x * 3 + 1], row[x * 3], 255],
+                _ => {
+                    let value = u32::from_le_bytes([row[x * 4], row[x * 4 + 1], row[x * 4 + 2], row[x * 4 + 3]]);
+                    let alpha = if masks[3] == 0 { 255 } else { channel(value, masks[3]) };
+                    [channel(value, masks[0]), channel(value, masks[1]), channel(value, masks[2]), alpha]
+                }
+            };
 
-    pointer.seek(SeekFrom::Start(header.bitmap_offset as u64))?;
-    for y in 0..header.height {
-        for x in 0..header.width {
-            pointer.read(&mut px)?;
-            data[(x + (header.height - y - 1) * header.width) as usize] = px[0] / (255 / NUM_COLORS);
+            pixels[x + dest_row * width as usize] = pixel;
         }
     }
 
-    return Ok(Bitmap { width: header.width, height: header.height, data: data });
+    return Ok((width, height, pixels));
+}
+
+// Sprite sheets are drawn in shades of the blue channel, each step of 255 / NUM_COLORS selects the next palette entry.
+pub fn bmp_load(data: &[u8]) -> Result<Bitmap, BMPError> {
+    let (width, height, pixels) = read_pixels(data)?;
+    let data = pixels.iter().map(|pixel| pixel[2] / (255 / NUM_COLORS)).collect();
+
+    return Ok(Bitmap { width: width, height: height, data: data, rgba: None });
+}
+
+pub fn bmp_load_true_color(data: &[u8]) -> Result<Bitmap, BMPError> {
+    let (width, height, pixels) = read_pixels(data)?;
+    let data = pixels.iter().map(|pixel| pixel[2] / (255 / NUM_COLORS)).collect();
+
+    return Ok(Bitmap { width: width, height: height, data: data, rgba: Some(pixels) });
+}
+
+// Errors carry the file name, without the directory.
+pub fn bmp_load_file(path: &Path, true_color: bool) -> Result<Bitmap, BMPError> {
+    let file_name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().to_string());
+    let data = std::fs::read(path).map_err(|error| BMPError::from(error).with_file_name(&file_name))?;
+    let bitmap = if true_color { bmp_load_true_color(&data) } else { bmp_load(&data) };
+    return bitmap.map_err(|error| error.with_file_name(&file_name));
+}
+
+// Writes 32 bpp BGRA pixel data, such as a BackBuffer, as a top-down bitmap. Without alpha the fourth byte of each pixel is
+// ignored by readers, with it a V4 header carrying an alpha mask is written.
+pub fn bmp_save<W: Write>(writer: &mut W, width: i32, height: i32, pixels: &[u8], alpha: bool) -> Result<(), BMPError> {
+    let image_size = (width * height * 4) as u32;
+    let header_size = if alpha { BMP_V4_HEADER_SIZE } else { BMP_INFO_HEADER_SIZE };
+
+    writer.write_u16::<BigEndian>(BMP_SIGNATURE)?;
+    writer.write_u32::<LittleEndian>(BMP_HEADER_SIZE + header_size + image_size)?;
+    writer.write_u32::<LittleEndian>(0)?;
+    writer.write_u32::<LittleEndian>(BMP_HEADER_SIZE + header_size)?;
+
+    writer.write_u32::<LittleEndian>(header_size)?;
+    writer.write_i32::<LittleEndian>(width)?;
+    writer.write_i32::<LittleEndian>(-height)?;
+    writer.write_u16::<LittleEndian>(1)?;
+    writer.write_u16::<LittleEndian>(32)?;
+    writer.write_u32::<LittleEndian>(if alpha { BI_BITFIELDS } else { BI_RGB })?;
+    writer.write_u32::<LittleEndian>(image_size)?;
+    writer.write_i32::<LittleEndian>(BMP_PIXELS_PER_METER)?;
+    writer.write_i32::<LittleEndian>(BMP_PIXELS_PER_METER)?;
+    writer.write_u32::<LittleEndian>(0)?;
+    writer.write_u32::<LittleEndian>(0)?;
+
+    if alpha {
+        for mask in [DEFAULT_MASKS[0], DEFAULT_MASKS[1], DEFAULT_MASKS[2], 0xff000000] {
+            writer.write_u32::<LittleEndian>(mask)?;
+        }
+
+        // Color space, followed by the unused endpoints and gamma.
+        writer.write_u32::<LittleEndian>(LCS_SRGB)?;
+        writer.write_all(&[0; 48])?;
+    }
+
+    writer.write_all(&pixels[..image_size as usize])?;
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Rgba = [0, 0, 0, 255];
+    const WHITE: Rgba = [255, 255, 255, 255];
+    const RED: Rgba = [255, 0, 0, 255];
+    const GREEN: Rgba = [0, 255, 0, 255];
+    const BLUE: Rgba = [0, 0, 255, 255];
+
+    // A file with an info header followed by header_tail, which holds the fields of newer headers, then the masks or
+    // color table in extra. Rows are padded to 4 bytes with junk the decoder has to skip.
+    fn bitmap(header_tail: &[u8], width: i32, height: i32, bits_per_pixel: u16, compression: u32, extra: &[u8], rows: &[&[u8]]) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&bits_per_pixel.to_le_bytes());
+        header.extend_from_slice(&compression.to_le_bytes());
+        header.extend_from_slice(&[0; 12]);
+        // Colors used, the table is only as long as what extra holds.
+        header.extend_from_slice(&(if bits_per_pixel <= 8 { extra.len() as u32 / 4 } else { 0 }).to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(header_tail);
+        return file(&header, extra, rows);
+    }
+
+    // An OS/2 core header, whose color table has three bytes per entry and always holds every color.
+    fn core_bitmap(width: u16, height: u16, bits_per_pixel: u16, colors: &[u8], rows: &[&[u8]]) -> Vec<u8> {
+        let mut header = Vec::new();
+        for value in [width, height, 1, bits_per_pixel] {
+            header.extend_from_slice(&value.to_le_bytes());
+        }
+        return file(&header, colors, rows);
+    }
+
+    fn file(header: &[u8], extra: &[u8], rows: &[&[u8]]) -> Vec<u8> {
+        let header_size = header.len() as u32 + 4;
+        let mut pixels = Vec::new();
+        for row in rows.iter() {
+            pixels.extend_from_slice(row);
+            pixels.resize(pixels.len().div_ceil(4) * 4, 0xEE);
+        }
+
+        let offset = BMP_HEADER_SIZE + header_size + extra.len() as u32;
+        let mut data = b"BM".to_vec();
+        data.extend_from_slice(&(offset + pixels.len() as u32).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&offset.to_le_bytes());
+        data.extend_from_slice(&header_size.to_le_bytes());
+        data.extend_from_slice(header);
+        data.extend_from_slice(extra);
+        data.extend_from_slice(&pixels);
+        return data;
+    }
+
+    fn table(colors: &[Rgba], entry_size: usize) -> Vec<u8> {
+        return colors.iter().flat_map(|color| [color[2], color[1], color[0], 0][..entry_size].to_vec()).collect();
+    }
+
+    fn masks(masks: &[u32]) -> Vec<u8> {
+        return masks.iter().flat_map(|mask| mask.to_le_bytes()).collect();
+    }
+
+    fn decode(data: &[u8]) -> (i32, i32, Vec<Rgba>) {
+        return read_pixels(data).unwrap_or_else(|error| panic!("{}", error));
+    }
+
+    #[test]
+    fn one_bit_bottom_up_with_padding() {
+        let data = bitmap(&[], 3, 2, 1, BI_RGB, &table(&[BLACK, WHITE], 4), &[&[0b1010_0000], &[0b0110_0000]]);
+        assert_eq!(decode(&data), (3, 2, vec![BLACK, WHITE, WHITE, WHITE, BLACK, WHITE]));
+    }
+
+    #[test]
+    fn four_bit_top_down() {
+        let data = bitmap(&[], 3, -2, 4, BI_RGB, &table(&[BLACK, RED, GREEN], 4), &[&[0x12, 0x00], &[0x21, 0x10]]);
+        assert_eq!(decode(&data), (3, 2, vec![RED, GREEN, BLACK, GREEN, RED, RED]));
+    }
+
+    #[test]
+    fn eight_bit_indices_past_the_table_are_black() {
+        let data = bitmap(&[], 3, 1, 8, BI_RGB, &table(&[WHITE, BLUE], 4), &[&[1, 0, 200]]);
+        assert_eq!(decode(&data), (3, 1, vec![BLUE, WHITE, BLACK]));
+    }
+
+    #[test]
+    fn twenty_four_bit_rows_are_padded() {
+        let data = bitmap(&[], 3, 2, 24, BI_RGB, &[], &[&[0, 0, 255, 0, 255, 0, 255, 0, 0], &[255, 255, 255, 0, 0, 0, 1, 2, 3]]);
+        assert_eq!(decode(&data), (3, 2, vec![WHITE, BLACK, [3, 2, 1, 255], RED, GREEN, BLUE]));
+    }
+
+    #[test]
+    fn thirty_two_bit_without_masks_is_opaque() {
+        let data = bitmap(&[], 2, -1, 32, BI_RGB, &[], &[&[1, 2, 3, 0, 4, 5, 6, 7]]);
+        assert_eq!(decode(&data), (2, 1, vec![[3, 2, 1, 255], [6, 5, 4, 255]]));
+    }
+
+    #[test]
+    fn bitfields_after_an_info_header() {
+        // 5-6-5 masks, which are scaled up to full bytes.
+        let data = bitmap(&[], 3, 1, 32, BI_BITFIELDS, &masks(&[0xf800, 0x07e0, 0x001f]), &[&[0x00, 0xf8, 0, 0, 0xe0, 0x07, 0, 0, 0x10, 0x84, 0, 0]]);
+        assert_eq!(decode(&data), (3, 1, vec![RED, GREEN, [132, 130, 132, 255]]));
+    }
+
+    #[test]
+    fn bitfields_with_alpha_in_a_v4_header() {
+        let mut tail = masks(&[0x000000ff, 0x0000ff00, 0x00ff0000, 0xff000000]);
+        tail.resize((BMP_V4_HEADER_SIZE - BMP_INFO_HEADER_SIZE) as usize, 0);
+        let data = bitmap(&tail, 2, 1, 32, BI_BITFIELDS, &[], &[&[10, 20, 30, 40, 1, 2, 3, 255]]);
+        assert_eq!(decode(&data), (2, 1, vec![[10, 20, 30, 40], [1, 2, 3, 255]]));
+    }
+
+    #[test]
+    fn core_headers_have_three_byte_colors() {
+        let data = core_bitmap(2, 2, 1, &table(&[RED, BLUE], 3), &[&[0b0100_0000], &[0b1100_0000]]);
+        assert_eq!(decode(&data), (2, 2, vec![BLUE, BLUE, RED, BLUE]));
+    }
+
+    fn decode_error(data: &[u8]) -> BMPError {
+        match read_pixels(data) {
+            Ok(_) => panic!("decoded a broken bitmap"),
+            Err(error) => return error,
+        }
+    }
+
+    #[test]
+    fn truncated_files() {
+        let data = bitmap(&[], 3, 2, 24, BI_RGB, &[], &[&[0; 9], &[0; 9]]);
+
+        for length in [0, 1, 13, 17, 30, 49] {
+            assert_eq!(decode_error(&data[..length]).kind, BMPErrorKind::Truncated, "{} bytes", length);
+        }
+
+        // The file size in the header says how much is missing.
+        let error = decode_error(&data[..data.len() - 1]);
+        assert_eq!(error.kind, BMPErrorKind::InvalidFileSize);
+        assert_eq!(error.with_file_name("keys.bmp").to_string(), "keys.bmp: invalid file size at offset 2: expected 78 bytes, found 77 bytes");
+
+        // Without it, the pixel data is checked on its own.
+        let mut short = data[..data.len() - 1].to_vec();
+        short[2..6].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(read_pixels(&short).unwrap_err().to_string(), "file is truncated at offset 54: expected 24 bytes of pixel data, found 23 bytes");
+        assert_eq!(decode_error(&data[..20]).to_string(), "file is truncated at offset 18: expected 4 bytes, found 2 bytes");
+    }
+
+    #[test]
+    fn bogus_headers() {
+        let data = bitmap(&[], 1, 1, 24, BI_RGB, &[], &[&[0; 3]]);
+        let with_header_size = |size: u32| {
+            let mut data = data.clone();
+            data[14..18].copy_from_slice(&size.to_le_bytes());
+            data
+        };
+
+        let error = decode_error(&with_header_size(0xFFFFFFF8)).with_file_name("keys.bmp");
+        assert_eq!(error.kind, BMPErrorKind::UnsupportedHeader);
+        assert_eq!(error.to_string(), "keys.bmp: unsupported header at offset 14: expected at most 44 bytes, found 4294967288 bytes");
+        assert_eq!(read_pixels(&with_header_size(u32::MAX)).unwrap_err().kind, BMPErrorKind::UnsupportedHeader);
+        assert_eq!(read_pixels(&with_header_size(45)).unwrap_err().kind, BMPErrorKind::UnsupportedHeader);
+        assert_eq!(read_pixels(&with_header_size(20)).unwrap_err().to_string(), "unsupported header at offset 14: expected 12 or at least 40 bytes, found 20 bytes");
+
+        let mut signature = data.clone();
+        signature[0] = b'P';
+        assert_eq!(read_pixels(&signature).unwrap_err().to_string(), "not a bitmap at offset 0: expected \"BM\", found 0x504d");
+
+        let mut offset = data.clone();
+        offset[10..14].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(read_pixels(&offset).unwrap_err().to_string(), "invalid pixel data offset at offset 10: expected 54 to 58, found 4294967295");
+
+        let mut bits = data.clone();
+        bits[28] = 16;
+        assert_eq!(read_pixels(&bits).unwrap_err().to_string(), "unsupported bits per pixel at offset 28: expected 1, 4, 8, 24 or 32, found 16");
+
+        let mut compression = data.clone();
+        compression[30] = 1;
+        assert_eq!(read_pixels(&compression).unwrap_err().kind, BMPErrorKind::UnsupportedCompression);
+
+        let mut width = data;
+        width[18..22].copy_from_slice(&(-1i32).to_le_bytes());
+        assert_eq!(read_pixels(&width).unwrap_err().to_string(), "invalid dimensions at offset 18: expected a positive width and a non-zero height, found -1x1");
+    }
+
+    #[test]
+    fn errors_name_the_file() {
+        let path = std::env::temp_dir().join(format!("gbc-input-display-{}-missing.bmp", std::process::id()));
+        let error = bmp_load_file(&path, false).err().unwrap();
+        assert_eq!(error.kind, BMPErrorKind::IOError);
+        assert!(error.to_string().starts_with(&format!("gbc-input-display-{}-missing.bmp: I/O error: ", std::process::id())), "{}", error);
+    }
+
+    #[test]
+    fn saved_frames_read_back() {
+        let pixels = [1, 2, 3, 255, 4, 5, 6, 128, 7, 8, 9, 0, 10, 11, 12, 255];
+        for alpha in [false, true] {
+            let mut data = Vec::new();
+            bmp_save(&mut data, 2, 2, &pixels, alpha).unwrap();
+            let expected: Vec<Rgba> = pixels.chunks(4).map(|pixel| [pixel[2], pixel[1], pixel[0], if alpha { pixel[3] } else { 255 }]).collect();
+            assert_eq!(decode(&data), (2, 2, expected));
+        }
+    }
 }
@@ -1,17 +1,30 @@
 use crate::application::*;
 use crate::gambatte::*;
-use crate::winapi::*;
+use crate::gfx::*;
+use crate::history::*;
+use crate::ini::*;
+use crate::input_log::*;
+use crate::keymap::*;
+use crate::notification::*;
+use crate::palette::*;
+use crate::registry::*;
+
+use std::collections::*;
+use std::io::*;
+use std::path::*;
+use std::time::*;
+
+pub fn configure_current_key(app: &mut Application, key_code: KeyCode) {
+    if key_code == KeyCode::None {
+        return;
+    }
 
-pub fn configure_current_key(key_code: u32) {
-    let app = unsafe { &mut *APP_POINTER };
     app.keys[app.key_to_configure as usize].primary_ipt = key_code;
-    app.keys[app.key_to_configure as usize].secondary_ipt = 0;
-    configure_next_key();
+    app.keys[app.key_to_configure as usize].secondary_ipt = KeyCode::None;
+    configure_next_key(app);
 }
 
-pub fn configure_next_key() {
-    let app = unsafe { &mut *APP_POINTER };
-
+pub fn configure_next_key(app: &mut Application) {
     if app.key_to_configure != -1 {
         app.keys[app.key_to_configure as usize].set_pressed(false);
     }
@@ -20,50 +33,342 @@ pub fn configure_next_key() {
 
     if app.key_to_configure >= app.keys.len() as i32 {
         app.key_to_configure = -1;
-        app.text_buffer = String::from("");
+        dismiss_notification(app, NotificationSource::Configuration);
         app.gambatte_sync = false;
-        save_configuration().ok();
+        save_configuration(app).ok();
     } else {
         app.keys[app.key_to_configure as usize].set_pressed(true);
-        app.text_buffer = String::from(format!("PRESS {}", &app.keys[app.key_to_configure as usize].name));
+        let text = format!("PRESS {}", &app.keys[app.key_to_configure as usize].name);
+        notify(app, Notification::new(&text, Priority::High, None, NotificationSource::Configuration));
+    }
+
+    draw_background(app);
+}
+
+// Everything that is persisted between sessions, independent of where it is stored.
+pub struct Configuration {
+    pub bindings: Vec<(String, KeyCode, KeyCode)>,
+    pub palette: String,
+    pub palettes: Vec<(String, Palette)>,
+    pub gambatte_sync: bool,
+    pub layout_path: Option<String>,
+    pub skin_path: Option<String>,
+    pub scale: i32,
+    pub history: bool,
+    pub history_length: Duration,
+    pub history_speed: i32,
+    pub input_log: bool,
+    pub input_log_length: usize,
+    pub press_counts: bool,
+    pub input_device: Option<String>,
+    pub gambatte_config: Option<String>,
+}
+
+const CONFIGURATION_DIRECTORY: &str = "inputdisplay";
+const CONFIGURATION_FILE: &str = "inputdisplay.ini";
+
+const KEYS_SECTION: &str = "keys";
+const DISPLAY_SECTION: &str = "display";
+const INPUT_SECTION: &str = "input";
+const GAMBATTE_SECTION: &str = "gambatte";
+const PALETTE_ENTRY: &str = "palette";
+const SYNC_ENTRY: &str = "gambatte_sync";
+const LAYOUT_ENTRY: &str = "layout";
+const SKIN_ENTRY: &str = "skin";
+const SCALE_ENTRY: &str = "scale";
+const HISTORY_ENTRY: &str = "history";
+const HISTORY_LENGTH_ENTRY: &str = "history_length";
+const HISTORY_SPEED_ENTRY: &str = "history_speed";
+const INPUT_LOG_ENTRY: &str = "input_log";
+const INPUT_LOG_LENGTH_ENTRY: &str = "input_log_length";
+const PRESS_COUNTS_ENTRY: &str = "press_counts";
+const DEVICE_ENTRY: &str = "device";
+const GAMBATTE_CONFIG_ENTRY: &str = "config";
+
+const LEGACY_REGISTRY_KEYS: &[&str] = &["SOFTWARE\\inputdisplay", "S"];
+const LEGACY_PALETTE_ENTRY: &str = "Palette";
+const LEGACY_SYNC_ENTRY: &str = "SyncGambatte";
+
+// Older versions stored the position of the palette in the list, which only ever held the built-in palettes.
+// Only registry values are positions, in the configuration file a palette named "1" is just that.
+fn legacy_palette_name(index: u32) -> String {
+    return BUILT_IN_PALETTES.get(index as usize).map_or(DEFAULT_PALETTE, |(name, _)| name).to_string();
+}
+
+impl Configuration {
+    pub fn from_ini(ini: &Ini) -> Configuration {
+        let mut configuration = Configuration {
+            bindings: Vec::new(),
+            palette: ini.get(DISPLAY_SECTION, PALETTE_ENTRY).map_or(DEFAULT_PALETTE.to_string(), |value| value.to_string()),
+            palettes: read_palettes(ini),
+            gambatte_sync: ini.get(DISPLAY_SECTION, SYNC_ENTRY) == Some("true"),
+            layout_path: ini.get(DISPLAY_SECTION, LAYOUT_ENTRY).map(|value| value.to_string()),
+            skin_path: ini.get(DISPLAY_SECTION, SKIN_ENTRY).map(|value| value.to_string()),
+            scale: ini.get(DISPLAY_SECTION, SCALE_ENTRY).and_then(|value| value.parse().ok()).filter(|scale| (1..=MAX_SCALE).contains(scale)).unwrap_or(DEFAULT_SCALE),
+            history: ini.get(DISPLAY_SECTION, HISTORY_ENTRY) == Some("true"),
+            history_length: ini.get(DISPLAY_SECTION, HISTORY_LENGTH_ENTRY).and_then(|value| value.parse::<f32>().ok()).filter(|seconds| *seconds > 0.0 && *seconds <= MAX_HISTORY_LENGTH.as_secs_f32()).map_or(DEFAULT_HISTORY_LENGTH, Duration::from_secs_f32),
+            history_speed: ini.get(DISPLAY_SECTION, HISTORY_SPEED_ENTRY).and_then(|value| value.parse().ok()).filter(|speed| (1..=MAX_HISTORY_SPEED).contains(speed)).unwrap_or(DEFAULT_HISTORY_SPEED),
+            input_log: ini.get(DISPLAY_SECTION, INPUT_LOG_ENTRY) == Some("true"),
+            input_log_length: ini.get(DISPLAY_SECTION, INPUT_LOG_LENGTH_ENTRY).and_then(|value| value.parse().ok()).filter(|length| (1..=MAX_INPUT_LOG_LENGTH).contains(length)).unwrap_or(DEFAULT_INPUT_LOG_LENGTH),
+            press_counts: ini.get(DISPLAY_SECTION, PRESS_COUNTS_ENTRY) == Some("true"),
+            input_device: ini.get(INPUT_SECTION, DEVICE_ENTRY).map(|value| value.to_string()),
+            gambatte_config: ini.get(GAMBATTE_SECTION, GAMBATTE_CONFIG_ENTRY).map(|value| value.to_string()),
+        };
+
+        for (name, value) in ini.section(KEYS_SECTION).unwrap_or(&[]).iter() {
+            let codes: Vec<KeyCode> = value.split(',').filter_map(KeyCode::from_name).collect();
+            configuration.bindings.push((name.clone(), codes.first().cloned().unwrap_or(KeyCode::None), codes.get(1).cloned().unwrap_or(KeyCode::None)));
+        }
+
+        return configuration;
     }
 
-    draw_background();
+    // Entries that are not part of the configuration are left alone, so hand edits to the file survive a save.
+    pub fn write_ini(&self, ini: &mut Ini) {
+        for (name, primary, secondary) in self.bindings.iter() {
+            let value = match (primary, secondary) {
+                (primary, KeyCode::None) => primary.to_string(),
+                (primary, secondary) => format!("{}, {}", primary, secondary),
+            };
+            ini.set(KEYS_SECTION, name, &value);
+        }
+
+        ini.set(DISPLAY_SECTION, PALETTE_ENTRY, &self.palette);
+        write_palettes(ini, &self.palettes);
+        ini.set(DISPLAY_SECTION, SYNC_ENTRY, if self.gambatte_sync { "true" } else { "false" });
+        ini.set(DISPLAY_SECTION, SCALE_ENTRY, &self.scale.to_string());
+        ini.set(DISPLAY_SECTION, HISTORY_ENTRY, if self.history { "true" } else { "false" });
+        ini.set(DISPLAY_SECTION, HISTORY_LENGTH_ENTRY, &self.history_length.as_secs_f32().to_string());
+        ini.set(DISPLAY_SECTION, HISTORY_SPEED_ENTRY, &self.history_speed.to_string());
+        ini.set(DISPLAY_SECTION, INPUT_LOG_ENTRY, if self.input_log { "true" } else { "false" });
+        ini.set(DISPLAY_SECTION, INPUT_LOG_LENGTH_ENTRY, &self.input_log_length.to_string());
+        ini.set(DISPLAY_SECTION, PRESS_COUNTS_ENTRY, if self.press_counts { "true" } else { "false" });
+
+        if let Some(path) = &self.layout_path {
+            ini.set(DISPLAY_SECTION, LAYOUT_ENTRY, path);
+        }
+
+        if let Some(path) = &self.skin_path {
+            ini.set(DISPLAY_SECTION, SKIN_ENTRY, path);
+        }
+
+        if let Some(device) = &self.input_device {
+            ini.set(INPUT_SECTION, DEVICE_ENTRY, device);
+        }
+
+        if let Some(path) = &self.gambatte_config {
+            ini.set(GAMBATTE_SECTION, GAMBATTE_CONFIG_ENTRY, path);
+        }
+    }
 }
 
-const PALETTE_ENTRY: &str = "Palette";
-const SYNC_ENTRY: &str = "SyncGambatte";
+// Converts the values older versions stored in the registry. `keys` pairs each key name with its registry entry prefix,
+// e.g. ("UP", "GameUpKey") for GameUpKey1 and GameUpKey2. Returns None when none of the known values are present.
+pub fn migrate_registry_values(values: &HashMap<String, u32>, keys: &[(&str, &str)]) -> Option<Configuration> {
+    let mut found = false;
+    let mut read = |name: &str| {
+        let value = values.get(name).cloned();
+        found |= value.is_some();
+        value
+    };
+
+    let mut configuration = Configuration {
+        bindings: Vec::new(),
+        palette: DEFAULT_PALETTE.to_string(),
+        palettes: Vec::new(),
+        gambatte_sync: false,
+        layout_path: None,
+        skin_path: None,
+        scale: DEFAULT_SCALE,
+        history: false,
+        history_length: DEFAULT_HISTORY_LENGTH,
+        history_speed: DEFAULT_HISTORY_SPEED,
+        input_log: false,
+        input_log_length: DEFAULT_INPUT_LOG_LENGTH,
+        press_counts: false,
+        input_device: None,
+        gambatte_config: None,
+    };
 
-pub fn load_configuration() -> std::io::Result<()> {
-    let app = unsafe { &mut *APP_POINTER };
+    for (name, reg_entry) in keys.iter() {
+        let primary = KeyCode::from_vk(read(&format!("{}{}", reg_entry, "1")).unwrap_or(0));
+        let secondary = KeyCode::from_vk(read(&format!("{}{}", reg_entry, "2")).unwrap_or(0));
+        configuration.bindings.push((name.to_string(), primary, secondary));
+    }
+
+    configuration.palette = read(LEGACY_PALETTE_ENTRY).map_or(DEFAULT_PALETTE.to_string(), legacy_palette_name);
+    configuration.gambatte_sync = read(LEGACY_SYNC_ENTRY).is_some_and(|sync| sync > 0);
 
-    let subkey = app.platform.reg_create_subkey(HKEY_CURRENT_USER, "SOFTWARE\\inputdisplay", KEY_QUERY_VALUE)?;
-    for key in app.keys.iter_mut() {
-        key.primary_ipt = app.platform.reg_read_u32(subkey, &format!("{}{}", key.reg_entry, "1"))?;
-        key.secondary_ipt = app.platform.reg_read_u32(subkey, &format!("{}{}", key.reg_entry, "2"))?;
+    if !found {
+        return None;
     }
 
-    change_palette(app.platform.reg_read_u32(subkey, PALETTE_ENTRY)? as usize);
-    if app.platform.reg_read_u32(subkey, SYNC_ENTRY)? > 0 {
-        sync_gambatte_keybindings().ok();
+    return Some(configuration);
+}
+
+// Older versions passed a wide string to RegCreateKeyExA, so their settings actually ended up in HKEY_CURRENT_USER\S.
+// The intended location is checked first in case it was ever written correctly.
+fn read_legacy_registry(keys: &[(&str, &str)]) -> HashMap<String, u32> {
+    let mut names = vec![LEGACY_PALETTE_ENTRY.to_string(), LEGACY_SYNC_ENTRY.to_string()];
+    for (_, reg_entry) in keys.iter() {
+        names.push(format!("{}{}", reg_entry, "1"));
+        names.push(format!("{}{}", reg_entry, "2"));
     }
 
-    app.platform.reg_close_subkey(subkey)?;
+    return first_legacy_values(|sub_key| {
+        let mut values = HashMap::new();
+        if let Ok(subkey) = RegistryKey::open(sub_key) {
+            for name in names.iter() {
+                if let Ok(value) = subkey.read_u32(name) {
+                    values.insert(name.clone(), value);
+                }
+            }
+        }
+        values
+    });
+}
 
-    Ok(())
+// The values of the first of LEGACY_REGISTRY_KEYS that holds any, the later keys are not read at all.
+fn first_legacy_values(read: impl FnMut(&str) -> HashMap<String, u32>) -> HashMap<String, u32> {
+    return LEGACY_REGISTRY_KEYS.iter().cloned().map(read).find(|values| !values.is_empty()).unwrap_or_default();
 }
 
-pub fn save_configuration() -> std::io::Result<()> {
-    let app = unsafe { &mut *APP_POINTER };
+// %APPDATA%\inputdisplay on Windows, $XDG_CONFIG_HOME/inputdisplay (or ~/.config/inputdisplay) everywhere else.
+pub fn configuration_path() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME").filter(|path| !path.is_empty()).map(PathBuf::from).or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+
+    return base.map(|base| base.join(CONFIGURATION_DIRECTORY).join(CONFIGURATION_FILE));
+}
 
-    let subkey = app.platform.reg_create_subkey(HKEY_CURRENT_USER, "SOFTWARE\\inputdisplay", KEY_SET_VALUE)?;
-    for key in app.keys.iter() {
-        app.platform.reg_write_u32(subkey, &format!("{}{}", key.reg_entry, "1"), key.primary_ipt)?;
-        app.platform.reg_write_u32(subkey, &format!("{}{}", key.reg_entry, "2"), key.secondary_ipt)?;
+fn read_ini() -> Result<Ini> {
+    let path = configuration_path().ok_or_else(|| Error::new(ErrorKind::NotFound, "no configuration directory"))?;
+    return Ok(Ini::parse(&std::fs::read_to_string(path)?));
+}
+
+pub fn read_configuration() -> Result<Configuration> {
+    return Ok(Configuration::from_ini(&read_ini()?));
+}
+
+// Used when there is no configuration file yet. Picks up the settings of older versions from the registry, if there are any.
+pub fn migrate_configuration(app: &Application) -> Option<Configuration> {
+    let keys: Vec<(&str, &str)> = app.keys.iter().filter(|key| !key.reg_entry.is_empty()).map(|key| (key.name.as_str(), key.reg_entry.as_str())).collect();
+    return migrate_registry_values(&read_legacy_registry(&keys), &keys);
+}
+
+pub fn load_configuration(app: &mut Application, configuration: &Configuration) {
+    for (name, primary, secondary) in configuration.bindings.iter() {
+        if let Some(key) = app.keys.iter_mut().find(|key| &key.name == name) {
+            key.primary_ipt = *primary;
+            key.secondary_ipt = *secondary;
+        }
+    }
+
+    app.layout_path = configuration.layout_path.clone();
+    app.skin_path = configuration.skin_path.clone();
+    app.input_device = configuration.input_device.clone();
+    app.gambatte_config = configuration.gambatte_config.clone();
+    app.input_log.visible = configuration.input_log;
+    app.input_log.length = configuration.input_log_length;
+    app.show_press_counts = configuration.press_counts;
+
+    app.palettes.truncate(BUILT_IN_PALETTES.len());
+    add_user_palettes(app, &configuration.palettes);
+    select_palette(app, &configuration.palette);
+    change_scale(app, configuration.scale);
+    if configuration.gambatte_sync {
+        sync_gambatte_keybindings(app).ok();
+    }
+}
+
+pub fn save_configuration(app: &Application) -> Result<()> {
+    let configuration = Configuration {
+        bindings: app.keys.iter().map(|key| (key.name.clone(), key.primary_ipt, key.secondary_ipt)).collect(),
+        palette: app.palettes[app.palette_index].0.clone(),
+        palettes: user_palettes(app),
+        gambatte_sync: app.gambatte_sync,
+        layout_path: app.layout_path.clone(),
+        skin_path: app.skin_path.clone(),
+        scale: app.scale,
+        history: app.history.visible,
+        history_length: app.history.length,
+        history_speed: app.history.speed,
+        input_log: app.input_log.visible,
+        input_log_length: app.input_log.length,
+        press_counts: app.show_press_counts,
+        input_device: app.input_device.clone(),
+        gambatte_config: app.gambatte_config.clone(),
+    };
+
+    let path = configuration_path().ok_or_else(|| Error::new(ErrorKind::NotFound, "no configuration directory"))?;
+    let mut ini = read_ini().unwrap_or_default();
+    configuration.write_ini(&mut ini);
+
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
     }
-    app.platform.reg_write_u32(subkey, PALETTE_ENTRY, app.palette_index as u32)?;
-    app.platform.reg_write_u32(subkey, SYNC_ENTRY, app.gambatte_sync as u32)?;
-    app.platform.reg_close_subkey(subkey)?;
+    std::fs::write(path, ini.to_string())?;
 
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &[(&str, &str)] = &[("UP", "GameUpKey"), ("A", "GameAKey")];
+
+    fn values(entries: &[(&str, u32)]) -> HashMap<String, u32> {
+        return entries.iter().map(|(name, value)| (name.to_string(), *value)).collect();
+    }
+
+    #[test]
+    fn nothing_to_migrate() {
+        assert!(migrate_registry_values(&HashMap::new(), KEYS).is_none());
+    }
+
+    #[test]
+    fn primary_and_secondary_bindings() {
+        let configuration = migrate_registry_values(&values(&[("GameUpKey1", 0x26), ("GameAKey1", 0x5A), ("GameAKey2", 0x58)]), KEYS).unwrap();
+        assert_eq!(configuration.bindings, vec![(String::from("UP"), KeyCode::Up, KeyCode::None), (String::from("A"), KeyCode::Z, KeyCode::X)]);
+    }
+
+    #[test]
+    fn palette_index_becomes_a_name() {
+        assert_eq!(migrate_registry_values(&values(&[("Palette", 0)]), KEYS).unwrap().palette, BUILT_IN_PALETTES[0].0);
+        assert_eq!(migrate_registry_values(&values(&[("Palette", 11)]), KEYS).unwrap().palette, "Inverted");
+        assert_eq!(migrate_registry_values(&values(&[("Palette", 1000)]), KEYS).unwrap().palette, DEFAULT_PALETTE);
+        assert_eq!(migrate_registry_values(&values(&[("GameUpKey1", 0x26)]), KEYS).unwrap().palette, DEFAULT_PALETTE);
+    }
+
+    #[test]
+    fn gambatte_sync_flag() {
+        assert!(migrate_registry_values(&values(&[("SyncGambatte", 1)]), KEYS).unwrap().gambatte_sync);
+        assert!(!migrate_registry_values(&values(&[("SyncGambatte", 0)]), KEYS).unwrap().gambatte_sync);
+        assert!(!migrate_registry_values(&values(&[("Palette", 2)]), KEYS).unwrap().gambatte_sync);
+    }
+
+    #[test]
+    fn truncated_key_is_read_when_the_intended_one_is_empty() {
+        let mut read = Vec::new();
+        let found = first_legacy_values(|sub_key| {
+            read.push(sub_key.to_string());
+            if sub_key == "S" { values(&[("Palette", 3)]) } else { HashMap::new() }
+        });
+        assert_eq!(found, values(&[("Palette", 3)]));
+        assert_eq!(read, LEGACY_REGISTRY_KEYS);
+
+        let mut read = Vec::new();
+        let found = first_legacy_values(|sub_key| {
+            read.push(sub_key.to_string());
+            values(&[("Palette", if sub_key == "S" { 3 } else { 4 })])
+        });
+        assert_eq!(found, values(&[("Palette", 4)]));
+        assert_eq!(read, &LEGACY_REGISTRY_KEYS[..1]);
+
+        assert!(first_legacy_values(|_| HashMap::new()).is_empty());
+    }
+}
@@ -30,7 +30,8 @@ pub struct Arrow {
 
 impl DpadKey {
     pub fn new(
-        idx: usize,
+        key_id: usize,
+        arrow_idx: i32,
         pressed_x_shift: i32,
         pressed_y_shift: i32,
         overhang_x_src: i32,
@@ -43,7 +44,7 @@ impl DpadKey {
         arrow_y_offset: i32,
     ) -> Self {
         return DpadKey {
-            key_id: idx,
+            key_id: key_id,
             pressed_x_shift: pressed_x_shift,
             pressed_y_shift: pressed_y_shift,
             overhang: Overhang {
@@ -55,7 +56,7 @@ impl DpadKey {
                 height: overhang_height,
             },
             arrow: Arrow {
-                idx: idx as i32,
+                idx: arrow_idx,
                 key_x_offset: arrow_x_offset,
                 key_y_offset: arrow_y_offset,
                 current_x_shift: 0,
@@ -65,9 +66,7 @@ impl DpadKey {
     }
 }
 
-pub fn update_dpad() {
-    let app = unsafe { &mut *APP_POINTER };
-
+pub fn update_dpad(app: &mut Application) {
     let mut total_x_shift = 0;
     let mut total_y_shift = 0;
 
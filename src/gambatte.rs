@@ -1,176 +1,244 @@
 use crate::application::*;
-use crate::winapi::*;
+use crate::ini::*;
+use crate::keymap::*;
+use crate::registry::*;
+
+use std::collections::*;
 use std::io::*;
+use std::path::*;
 
-macro_rules! qt_translate {
-    ($x:expr) => {
-        match $x {
-            0x01020001 => 0x03,
-            0x01000003 => 0x08,
-            0x01000001 => 0x09,
-            0x0100000b => 0x0C,
-            0x01000004 => 0x0D,
-            0x01000008 => 0x13,
-            0x01000024 => 0x14,
-            0x01000000 => 0x1B,
-            0x0100117e => 0x1F,
-            0x20 => 0x20,
-            0x01000016 => 0x21,
-            0x01000017 => 0x22,
-            0x01000011 => 0x23,
-            0x01000010 => 0x24,
-            0x01000012 => 0x25,
-            0x01000013 => 0x26,
-            0x01000014 => 0x27,
-            0x01000015 => 0x28,
-            0x01010000 => 0x29,
-            0x01020002 => 0x2A,
-            0x01020003 => 0x2B,
-            0x01000009 => 0x2C,
-            0x01000006 => 0x2D,
-            0x01000007 => 0x2E,
-            0x01000058 => 0x2F,
-            0x30 => 0x30,
-            0x31 => 0x31,
-            0x32 => 0x32,
-            0x33 => 0x33,
-            0x34 => 0x34,
-            0x35 => 0x35,
-            0x36 => 0x36,
-            0x37 => 0x37,
-            0x38 => 0x38,
-            0x39 => 0x39,
-            0x41 => 0x41,
-            0x42 => 0x42,
-            0x43 => 0x43,
-            0x44 => 0x44,
-            0x45 => 0x45,
-            0x46 => 0x46,
-            0x47 => 0x47,
-            0x48 => 0x48,
-            0x49 => 0x49,
-            0x4A => 0x4A,
-            0x4B => 0x4B,
-            0x4C => 0x4C,
-            0x4D => 0x4D,
-            0x4E => 0x4E,
-            0x4F => 0x4F,
-            0x50 => 0x50,
-            0x51 => 0x51,
-            0x52 => 0x52,
-            0x53 => 0x53,
-            0x54 => 0x54,
-            0x55 => 0x55,
-            0x56 => 0x56,
-            0x57 => 0x57,
-            0x58 => 0x58,
-            0x59 => 0x59,
-            0x5A => 0x5A,
-            0x01000022 => 0x5B,
-            0x01000055 => 0x5D,
-            0x01020004 => 0x5F,
-            0x2a => 0x6A,
-            0x2b => 0x6B,
-            0x01000030 => 0x70,
-            0x01000031 => 0x71,
-            0x01000032 => 0x72,
-            0x01000033 => 0x73,
-            0x01000034 => 0x74,
-            0x01000035 => 0x75,
-            0x01000036 => 0x76,
-            0x01000037 => 0x77,
-            0x01000038 => 0x78,
-            0x01000039 => 0x79,
-            0x0100003a => 0x7A,
-            0x0100003b => 0x7B,
-            0x0100003c => 0x7C,
-            0x0100003d => 0x7D,
-            0x0100003e => 0x7E,
-            0x0100003f => 0x7F,
-            0x01000040 => 0x80,
-            0x01000041 => 0x81,
-            0x01000042 => 0x82,
-            0x01000043 => 0x83,
-            0x01000044 => 0x84,
-            0x01000045 => 0x85,
-            0x01000046 => 0x86,
-            0x01000047 => 0x87,
-            0x01000025 => 0x90,
-            0x01000026 => 0x91,
-            0x92 => 0x92,
-            0x0100112c => 0x93,
-            0x0100112b => 0x94,
-            0x95 => 0x95,
-            0x96 => 0x96,
-            0x01000061 => 0xA6,
-            0x01000062 => 0xA7,
-            0x01000064 => 0xA8,
-            0x01000063 => 0xA9,
-            0x01000092 => 0xAA,
-            0x01000091 => 0xAB,
-            0x01000090 => 0xAC,
-            0x01000071 => 0xAD,
-            0x01000070 => 0xAE,
-            0x01000072 => 0xAF,
-            0x01000083 => 0xB0,
-            0x01000082 => 0xB1,
-            0x01000081 => 0xB2,
-            0x01000080 => 0xB3,
-            0x010000a0 => 0xB4,
-            0x010000a1 => 0xB5,
-            0x010000a2 => 0xB6,
-            0x010000a3 => 0xB7,
-            0xBA => 0xBA,
-            0xBB => 0xBB,
-            0xBC => 0xBC,
-            0xBD => 0xBD,
-            0xBE => 0xBE,
-            0xBF => 0xBF,
-            0xC0 => 0xC0,
-            0xDB => 0xDB,
-            0xDC => 0xDC,
-            0xDD => 0xDD,
-            0xDE => 0xDE,
-            0xDF => 0xDF,
-            0x01020005 => 0xFA,
-            0x01020006 => 0xFB,
-            0x3b => 186,
-            0x3a => 186,
-            0x3d => 187,
-            0x3c => 188,
-            0x5f => 189,
-            0x3e => 190,
-            0x22 => 222,
-            0x2c => 188,
-            0x2e => 190,
-            0x2f => 191,
-            0x5c => 220,
-            0x27 => 222,
-            0x5b => 219,
-            0x5d => 221,
-            0x2d => 189,
-            0x01000020 => 160,
-            0x01000021 => 162,
-            0x01000023 => 164,
-            _ => 0x00,
-        };
-    };
+const GAMBATTE_REGISTRY_KEY: &str = "SOFTWARE\\gambatte\\gambatte_qt\\input";
+const GAMBATTE_INPUT_GROUP: &str = "input/";
+
+const QT_VARIANT_INT: u32 = 2;
+const QT_VARIANT_UINT: u32 = 3;
+
+// Where Gambatte's Qt frontend keeps its settings outside of Windows: $XDG_CONFIG_HOME/gambatte/gambatte_qt.conf.
+pub fn default_gambatte_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME").filter(|path| !path.is_empty()).map(PathBuf::from).or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    return Some(base.join("gambatte").join("gambatte_qt.conf"));
 }
 
-pub fn sync_gambatte_keybindings() -> Result<()> {
-    let app = unsafe { &mut *APP_POINTER };
+// QSettings escapes key characters outside of [A-Za-z0-9_-/\\] as %XX, or %UXXXX for anything beyond Latin-1.
+fn unescape_qt_key(key: &str) -> String {
+    let mut result = String::new();
+    let mut chars = key.chars().peekable();
 
-    let subkey = app.platform.reg_open_subkey(HKEY_CURRENT_USER, "SOFTWARE\\gambatte\\gambatte_qt\\input", KEY_QUERY_VALUE)?;
-    for key in app.keys.iter_mut() {
-        key.primary_ipt = qt_translate!(app.platform.reg_read_u32(subkey, &format!("{}{}", &key.reg_entry, "1"))?);
-        if key.primary_ipt == 160 {
-            key.secondary_ipt = 161
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+
+        let digits = if chars.peek() == Some(&'U') {
+            chars.next();
+            4
         } else {
-            key.secondary_ipt = qt_translate!(app.platform.reg_read_u32(subkey, &format!("{}{}", &key.reg_entry, "2"))?);
+            2
+        };
+        let hex: String = chars.by_ref().take(digits).collect();
+        match u32::from_str_radix(&hex, 16).ok().and_then(std::char::from_u32) {
+            Some(decoded) => result.push(decoded),
+            None => {
+                result.push('%');
+                result.push_str(&hex);
+            }
+        }
+    }
+
+    return result;
+}
+
+// Undoes the C-style escaping QSettings uses for values. Quotes only group text and are dropped.
+fn unescape_qt_value(value: &str) -> Vec<u32> {
+    let mut result = Vec::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {}
+            '\\' => match chars.next() {
+                Some('x') => {
+                    let mut code = 0;
+                    for _ in 0..4 {
+                        match chars.peek().and_then(|c| c.to_digit(16)) {
+                            Some(digit) => {
+                                code = code * 16 + digit;
+                                chars.next();
+                            }
+                            None => break,
+                        }
+                    }
+                    result.push(code);
+                }
+                Some('0') => result.push(0),
+                Some('a') => result.push(0x07),
+                Some('b') => result.push(0x08),
+                Some('f') => result.push(0x0C),
+                Some('n') => result.push(0x0A),
+                Some('r') => result.push(0x0D),
+                Some('t') => result.push(0x09),
+                Some('v') => result.push(0x0B),
+                Some(other) => result.push(other as u32),
+                None => {}
+            },
+            _ => result.push(c as u32),
+        }
+    }
+
+    return result;
+}
+
+// Key codes are usually plain integers, but can also be written as a quoted string or a serialized @Variant(...).
+pub fn decode_qt_value(value: &str) -> Option<u32> {
+    let value = value.trim();
+
+    if value.starts_with("@Variant(") && value.ends_with(')') {
+        let bytes: Vec<u8> = unescape_qt_value(&value[9..value.len() - 1]).iter().map(|&c| c as u8).collect();
+        if bytes.len() < 8 {
+            return None;
+        }
+
+        // QDataStream layout: a big endian type id, a null flag since Qt 4.8 (Qt 5 always writes it), then the value.
+        let type_id = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if type_id != QT_VARIANT_INT && type_id != QT_VARIANT_UINT {
+            return None;
+        }
+
+        let data = &bytes[bytes.len() - 4..];
+        return Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+    }
+
+    let text: String = unescape_qt_value(value).iter().filter_map(|&c| std::char::from_u32(c)).collect();
+    let text = text.trim();
+    return text.parse::<u32>().ok().or_else(|| text.parse::<i32>().ok().map(|code| code as u32));
+}
+
+// Collects every value of the input group, keyed by its name within the group (e.g. "GameUpKey1").
+// Qt writes the group either as an [input] section or as "input\\GameUpKey1" keys in [General].
+pub fn read_qt_settings(path: &Path) -> Result<HashMap<String, u32>> {
+    return Ok(parse_qt_settings(&std::fs::read_to_string(path)?));
+}
+
+fn parse_qt_settings(text: &str) -> HashMap<String, u32> {
+    let ini = Ini::parse(text);
+    let mut values = HashMap::new();
+
+    for (section, entries) in ini.sections.iter() {
+        for (key, value) in entries.iter() {
+            let name = if section.is_empty() || section == "General" { unescape_qt_key(key) } else { format!("{}/{}", unescape_qt_key(section), unescape_qt_key(key)) };
+            let name = name.replace('\\', "/");
+
+            if let (Some(name), Some(code)) = (name.strip_prefix(GAMBATTE_INPUT_GROUP), decode_qt_value(value)) {
+                values.insert(name.to_string(), code);
+            }
+        }
+    }
+
+    return values;
+}
+
+// The bindings for the Gambatte entry of a key, or None to keep the current ones when Gambatte has no usable value for it.
+// Keys this program has no name for cannot be bound, they are left unbound.
+fn gambatte_binding(values: &HashMap<String, u32>, reg_entry: &str) -> Option<(KeyCode, KeyCode)> {
+    let primary = KeyCode::from_qt(*values.get(&format!("{}{}", reg_entry, "1"))?).unwrap_or(KeyCode::None);
+    if primary == KeyCode::LShift {
+        return Some((primary, KeyCode::RShift));
+    }
+
+    return Some((primary, values.get(&format!("{}{}", reg_entry, "2")).and_then(|code| KeyCode::from_qt(*code)).unwrap_or(KeyCode::None)));
+}
+
+fn read_registry_settings(names: &[String]) -> Result<HashMap<String, u32>> {
+    let subkey = RegistryKey::open(GAMBATTE_REGISTRY_KEY)?;
+    let mut values = HashMap::new();
+
+    for name in names.iter() {
+        if let Ok(value) = subkey.read_u32(name) {
+            values.insert(name.clone(), value);
+        }
+    }
+
+    return Ok(values);
+}
+
+pub fn sync_gambatte_keybindings(app: &mut Application) -> Result<()> {
+    // Keys without a Gambatte entry in the layout keep their bindings.
+    let mut names = Vec::new();
+    for key in app.keys.iter().filter(|key| !key.reg_entry.is_empty()) {
+        names.push(format!("{}{}", &key.reg_entry, "1"));
+        names.push(format!("{}{}", &key.reg_entry, "2"));
+    }
+
+    let values = match &app.gambatte_config {
+        Some(path) => read_qt_settings(Path::new(path))?,
+        None if cfg!(windows) => read_registry_settings(&names)?,
+        None => read_qt_settings(&default_gambatte_config_path().ok_or_else(|| Error::new(ErrorKind::NotFound, "no configuration directory"))?)?,
+    };
+
+    // Not every emulator knows every key, e.g. only the GBA ones have GameLKey. Keys it has no entries for are left alone,
+    // but finding none at all means this is not the right configuration.
+    if !names.iter().any(|name| values.contains_key(name)) {
+        return Err(Error::new(ErrorKind::NotFound, "no key bindings found"));
+    }
+
+    for key in app.keys.iter_mut().filter(|key| !key.reg_entry.is_empty()) {
+        if let Some((primary, secondary)) = gambatte_binding(&values, &key.reg_entry) {
+            key.primary_ipt = primary;
+            key.secondary_ipt = secondary;
         }
     }
-    app.platform.reg_close_subkey(subkey)?;
 
     app.gambatte_sync = true;
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_integers() {
+        assert_eq!(decode_qt_value("90"), Some(90));
+        assert_eq!(decode_qt_value(" 16777234 "), Some(0x01000012));
+        assert_eq!(decode_qt_value("-1"), Some(u32::MAX));
+        assert_eq!(decode_qt_value("Z"), None);
+    }
+
+    #[test]
+    fn quoted_values_with_escapes() {
+        assert_eq!(decode_qt_value("\"88\""), Some(88));
+        assert_eq!(decode_qt_value("\"\\x38\\x38\""), Some(88));
+        assert_eq!(unescape_qt_value("\"a\\tb\\\\c\\\"\""), vec!['a' as u32, 9, 'b' as u32, '\\' as u32, 'c' as u32, '"' as u32]);
+    }
+
+    #[test]
+    fn variant_ints() {
+        assert_eq!(decode_qt_value("@Variant(\\0\\0\\0\\x2\\0\\0\\0\\0Z)"), Some(90));
+        assert_eq!(decode_qt_value("@Variant(\\0\\0\\0\\x3\\0\\x1\\0\\0\\x12)"), Some(0x01000012));
+        assert_eq!(decode_qt_value("@Variant(\\0\\0\\0\\n\\0\\0\\0\\0Z)"), None);
+        assert_eq!(decode_qt_value("@Variant(\\0\\0)"), None);
+    }
+
+    #[test]
+    fn escaped_keys() {
+        assert_eq!(unescape_qt_key("Game%20Up%U00e9Key"), "Game Up\u{e9}Key");
+        assert_eq!(unescape_qt_key("100%zz"), "100%zz");
+    }
+
+    #[test]
+    fn general_and_named_sections() {
+        let values = parse_qt_settings("[General]\ninput\\GameUpKey1=16777235\n\n[input]\nGameAKey1=90\nGameAKey2=\"88\"\n\n[video]\nGameBKey1=66\n");
+        assert_eq!(values, [("GameUpKey1", 0x01000013), ("GameAKey1", 90), ("GameAKey2", 88)].iter().map(|(name, code)| (name.to_string(), *code)).collect());
+    }
+
+    #[test]
+    fn missing_or_garbled_entries_keep_the_binding() {
+        let values = parse_qt_settings("[input]\nGameAKey1=90\nGameAKey2=88\nGameBKey1=garbled\nGameStartKey1=16777248\nGameSelectKey1=1\n");
+        assert_eq!(gambatte_binding(&values, "GameAKey"), Some((KeyCode::Z, KeyCode::X)));
+        assert_eq!(gambatte_binding(&values, "GameBKey"), None);
+        assert_eq!(gambatte_binding(&values, "GameUpKey"), None);
+        assert_eq!(gambatte_binding(&values, "GameStartKey"), Some((KeyCode::LShift, KeyCode::RShift)));
+        assert_eq!(gambatte_binding(&values, "GameSelectKey"), Some((KeyCode::None, KeyCode::None)));
+    }
+}
@@ -1,18 +1,22 @@
 use crate::bmp::*;
-use crate::winapi::*;
 use std::ops::*;
 
 pub type Color = [u8; 3];
 pub type Palette = Vec<Color>;
 
-const BIT_COUNT: i32 = 32;
-const BYTES_PER_PIXEL: i32 = BIT_COUNT / 8;
+pub const BYTES_PER_PIXEL: i32 = 4;
 
+// The palette entry sprites use around their edges, which blends with the window background. See change_palette.
+pub const BACKGROUND_INDEX: usize = 4;
+
+// BGRA pixels. With a transparent background the background and everything drawn in BACKGROUND_INDEX get an alpha of 0,
+// otherwise every pixel is opaque.
+#[derive(Clone)]
 pub struct BackBuffer {
     pub width: i32,
     pub height: i32,
-    pub info: BitmapInfo,
     pub memory: Vec<u8>,
+    pub transparent_background: bool,
 }
 
 pub struct SpriteSheet {
@@ -22,6 +26,18 @@ pub struct SpriteSheet {
     pub sprites_per_row: i32,
 }
 
+// A font sheet holds the printable ASCII characters from FIRST_GLYPH to LAST_GLYPH in order, followed by the glyph drawn for
+// every other character. Each glyph advances the text by its own width, which can be narrower than the sprite.
+pub const FIRST_GLYPH: char = ' ';
+pub const LAST_GLYPH: char = '~';
+pub const GLYPH_COUNT: i32 = LAST_GLYPH as i32 - FIRST_GLYPH as i32 + 2;
+const FALLBACK_GLYPH: i32 = GLYPH_COUNT - 1;
+
+pub struct Font {
+    pub sheet: SpriteSheet,
+    pub widths: Vec<i32>,
+}
+
 impl Index<usize> for BackBuffer {
     type Output = u8;
 
@@ -36,43 +52,107 @@ impl IndexMut<usize> for BackBuffer {
     }
 }
 
+// One axis of a copy, shrunk so it starts and ends inside both the destination and the source.
+// Returns the new destination and source positions and the length, or None when nothing is left.
+// Worked out in i64, so no position or length can overflow. Whatever is left lies inside both sizes and fits an i32 again.
+fn clip_span(dest_size: i32, src_size: i32, dest: i32, src: i32, length: i32) -> Option<(i32, i32, i32)> {
+    let (dest_size, src_size, dest, src, length) = (dest_size as i64, src_size as i64, dest as i64, src as i64, length as i64);
+    let skip = (-dest).max(-src).max(0);
+    let dest = dest + skip;
+    let src = src + skip;
+    let length = (length - skip).min(dest_size - dest).min(src_size - src);
+
+    if length <= 0 {
+        return None;
+    }
+
+    return Some((dest as i32, src as i32, length as i32));
+}
+
 impl SpriteSheet {
     pub fn new(bitmap: Bitmap, sprite_width: i32, sprite_height: i32) -> Self {
         return SpriteSheet { sprites_per_row: bitmap.width / sprite_width, sprite_width: sprite_width, sprite_height: sprite_height, bitmap: bitmap };
     }
 }
 
+impl Font {
+    // Widths past the end of the list, or wider than a sprite, are taken as the sprite width.
+    pub fn new(sheet: SpriteSheet, widths: Vec<i32>) -> Font {
+        let widths = (0..GLYPH_COUNT as usize).map(|glyph| widths.get(glyph).map_or(sheet.sprite_width, |width| (*width).min(sheet.sprite_width))).collect();
+        return Font { sheet: sheet, widths: widths };
+    }
+
+    pub fn glyph(&self, character: char) -> i32 {
+        if (FIRST_GLYPH..=LAST_GLYPH).contains(&character) {
+            return character as i32 - FIRST_GLYPH as i32;
+        }
+
+        return FALLBACK_GLYPH;
+    }
+
+    pub fn text_width(&self, text: &str) -> i32 {
+        return text.chars().map(|character| self.widths[self.glyph(character) as usize]).sum();
+    }
+}
+
 impl BackBuffer {
     pub fn new(width: i32, height: i32) -> BackBuffer {
         return BackBuffer {
             width: width,
             height: height,
-            info: BitmapInfo {
-                header: BitmapInfoHeader {
-                    size: std::mem::size_of::<BitmapInfoHeader>() as u32,
-                    width: width,
-                    height: -height,
-                    planes: 1,
-                    bit_count: BIT_COUNT as u16,
-                    compression: BI_RGB,
-                    size_image: 0,
-                    x_pels_per_meter: 0,
-                    y_pels_per_meter: 0,
-                    colors_used: 0,
-                    colors_important: 0,
-                },
-                colors: 0,
-            },
             memory: vec![0; (width * height * BYTES_PER_PIXEL) as usize],
+            transparent_background: false,
         };
     }
 
+    // Nearest neighbour upscaling, every pixel becomes a scale x scale block.
+    pub fn scaled(&self, scale: i32) -> BackBuffer {
+        let mut scaled = BackBuffer::new(self.width * scale, self.height * scale);
+        scaled.transparent_background = self.transparent_background;
+
+        for y in 0..scaled.height {
+            let src_row = ((y / scale) * self.width * BYTES_PER_PIXEL) as usize;
+            let dest_row = (y * scaled.width * BYTES_PER_PIXEL) as usize;
+            for x in 0..scaled.width {
+                let src = src_row + ((x / scale) * BYTES_PER_PIXEL) as usize;
+                let dest = dest_row + (x * BYTES_PER_PIXEL) as usize;
+                scaled.memory[dest..dest + BYTES_PER_PIXEL as usize].copy_from_slice(&self.memory[src..src + BYTES_PER_PIXEL as usize]);
+            }
+        }
+
+        return scaled;
+    }
+
     pub fn clear(&mut self, color: Color) {
+        let alpha = if self.transparent_background { 0 } else { 255 };
         for i in 0..self.width * self.height {
             let index = (i * BYTES_PER_PIXEL) as usize;
-            self[index + 0] = color[2];
+            self[index] = color[2];
             self[index + 1] = color[1];
             self[index + 2] = color[0];
+            self[index + 3] = alpha;
+        }
+    }
+
+    // Opaque, whatever lies outside the buffer is skipped.
+    pub fn fill_rect(&mut self, color: Color, x_dest: i32, y_dest: i32, width: i32, height: i32) {
+        let (x_dest, _, width) = match clip_span(self.width, width, x_dest, 0, width) {
+            Some(span) => span,
+            None => return,
+        };
+        let (y_dest, _, height) = match clip_span(self.height, height, y_dest, 0, height) {
+            Some(span) => span,
+            None => return,
+        };
+
+        for y in y_dest..y_dest + height {
+            let row = ((x_dest + y * self.width) * BYTES_PER_PIXEL) as usize;
+            for dest in (row..row + (width * BYTES_PER_PIXEL) as usize).step_by(BYTES_PER_PIXEL as usize) {
+                self[dest] = color[2];
+                self[dest + 1] = color[1];
+                self[dest + 2] = color[0];
+                self[dest + 3] = 255;
+            }
         }
     }
 
@@ -80,51 +160,319 @@ impl BackBuffer {
         self.draw_subsprite(palette, spritesheet, x_dest, y_dest, index, 0, 0, spritesheet.sprite_width, spritesheet.sprite_height);
     }
 
+    // Negative indices draw nothing, as do indices past the end of the sheet. The source position is worked out in i64, anything that
+    // does not fit an i32 lies far outside the bitmap and is clipped away like any other position outside it.
     pub fn draw_subsprite(&mut self, palette: &Palette, spritesheet: &SpriteSheet, x_dest: i32, y_dest: i32, index: i32, x_src_offs: i32, y_src_offs: i32, width: i32, height: i32) {
-        self.draw_subbitmap(
-            palette,
-            &spritesheet.bitmap,
-            x_dest,
-            y_dest,
-            (index % spritesheet.sprites_per_row) * spritesheet.sprite_width + x_src_offs,
-            (index / spritesheet.sprites_per_row) * spritesheet.sprite_height + y_src_offs,
-            width,
-            height,
-        );
-    }
-
-    pub fn draw_text(&mut self, palette: &Palette, font: &SpriteSheet, text: &str, x_dest: i32, y_dest: i32) {
+        if index < 0 || spritesheet.sprites_per_row <= 0 {
+            return;
+        }
+
+        let x_src = (index % spritesheet.sprites_per_row) as i64 * spritesheet.sprite_width as i64 + x_src_offs as i64;
+        let y_src = (index / spritesheet.sprites_per_row) as i64 * spritesheet.sprite_height as i64 + y_src_offs as i64;
+        let clamp = |position: i64| position.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        self.draw_subbitmap(palette, &spritesheet.bitmap, x_dest, y_dest, clamp(x_src), clamp(y_src), width, height);
+    }
+
+    // Only the width of each glyph is drawn, so a narrow glyph does not cover the one before it.
+    pub fn draw_text(&mut self, palette: &Palette, font: &Font, text: &str, x_dest: i32, y_dest: i32) {
         let mut x = x_dest;
 
         for character in text.chars() {
+            let glyph = font.glyph(character);
+            let width = font.widths[glyph as usize];
             if character != ' ' {
-                self.draw_sprite(palette, font, x, y_dest, character as i32 - 'A' as i32);
+                self.draw_subsprite(palette, &font.sheet, x, y_dest, glyph, 0, 0, width, font.sheet.sprite_height);
             }
 
-            x += font.sprite_width;
+            x = x.saturating_add(width);
         }
     }
 
+    // True colour bitmaps ignore the palette. Whatever lies outside the buffer or the bitmap is skipped.
     pub fn draw_subbitmap(&mut self, palette: &Palette, bitmap: &Bitmap, x_dest: i32, y_dest: i32, x_src: i32, y_src: i32, width: i32, height: i32) {
+        if let Some(pixels) = &bitmap.rgba {
+            self.draw_subbitmap_rgba(pixels, bitmap.width, x_dest, y_dest, x_src, y_src, width, height);
+            return;
+        }
+
+        let (x_dest, x_src, width) = match clip_span(self.width, bitmap.width, x_dest, x_src, width) {
+            Some(span) => span,
+            None => return,
+        };
+        let (y_dest, y_src, height) = match clip_span(self.height, bitmap.height, y_dest, y_src, height) {
+            Some(span) => span,
+            None => return,
+        };
+
         let mut src_row = (x_src + y_src * bitmap.width) as usize;
         let mut dest_row = ((x_dest + y_dest * self.width) * BYTES_PER_PIXEL) as usize;
         for _y in 0..height {
-            let mut src = src_row;
             let mut dest = dest_row;
 
-            for _x in 0..width {
-                let palette_index = bitmap[src] as usize;
-                src += 1;
+            for x in 0..width as usize {
+                let palette_index = bitmap[src_row + x] as usize;
                 self[dest] = palette[palette_index][2];
                 dest += 1;
                 self[dest] = palette[palette_index][1];
                 dest += 1;
                 self[dest] = palette[palette_index][0];
-                dest += 2;
+                dest += 1;
+                self[dest] = if self.transparent_background && palette_index == BACKGROUND_INDEX { 0 } else { 255 };
+                dest += 1;
             }
 
             src_row += bitmap.width as usize;
             dest_row += (self.width * BYTES_PER_PIXEL) as usize;
         }
     }
+
+    // Composites the pixels over what is already there by their alpha, which over a transparent background leaves partly transparent pixels.
+    pub fn draw_subbitmap_rgba(&mut self, pixels: &[Rgba], pixels_per_row: i32, x_dest: i32, y_dest: i32, x_src: i32, y_src: i32, width: i32, height: i32) {
+        let (x_dest, x_src, width) = match clip_span(self.width, pixels_per_row, x_dest, x_src, width) {
+            Some(span) => span,
+            None => return,
+        };
+        let (y_dest, y_src, height) = match clip_span(self.height, pixels.len() as i32 / pixels_per_row.max(1), y_dest, y_src, height) {
+            Some(span) => span,
+            None => return,
+        };
+
+        let mut src_row = (x_src + y_src * pixels_per_row) as usize;
+        let mut dest_row = ((x_dest + y_dest * self.width) * BYTES_PER_PIXEL) as usize;
+        for _y in 0..height {
+            let mut dest = dest_row;
+
+            for x in 0..width as usize {
+                let [r, g, b, src_alpha] = pixels[src_row + x];
+                let src_alpha = src_alpha as u32;
+                let dest_alpha = self[dest + 3] as u32 * (255 - src_alpha) / 255;
+                let alpha = src_alpha + dest_alpha;
+                let blend = |src: u8, dest: u8| (src as u32 * src_alpha + dest as u32 * dest_alpha + alpha / 2).checked_div(alpha).unwrap_or(0) as u8;
+                self[dest] = blend(b, self[dest]);
+                self[dest + 1] = blend(g, self[dest + 1]);
+                self[dest + 2] = blend(r, self[dest + 2]);
+                self[dest + 3] = alpha as u8;
+                dest += BYTES_PER_PIXEL as usize;
+            }
+
+            src_row += pixels_per_row as usize;
+            dest_row += (self.width * BYTES_PER_PIXEL) as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: i32 = 7;
+    const HEIGHT: i32 = 5;
+    const UNTOUCHED: u8 = 0xAA;
+    const ROUNDS: usize = 20000;
+
+    // A linear congruential generator, enough to sweep the rectangles the same way on every run.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> u32 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            return (self.0 >> 33) as u32;
+        }
+
+        // Mostly small values around the buffer, sometimes the extremes.
+        fn coordinate(&mut self) -> i32 {
+            const EXTREMES: &[i32] = &[i32::MIN, i32::MIN + 1, i32::MIN / 2, -1000, 1000, i32::MAX / 2, i32::MAX - 1, i32::MAX];
+            match self.next() % 4 {
+                0 => return EXTREMES[self.next() as usize % EXTREMES.len()],
+                1 => return self.next() as i32,
+                _ => return (self.next() % 24) as i32 - 12,
+            }
+        }
+    }
+
+    fn buffer() -> BackBuffer {
+        let mut buffer = BackBuffer::new(WIDTH, HEIGHT);
+        buffer.memory.fill(UNTOUCHED);
+        return buffer;
+    }
+
+    // The destination pixels a copy may write: inside the buffer, the rectangle and the source moved to the destination.
+    fn expected_span(dest_size: i32, src_size: i32, dest: i32, src: i64, length: i32) -> std::ops::Range<i64> {
+        let offset = dest as i64 - src;
+        return (dest as i64).max(offset).max(0)..(dest as i64 + length as i64).min(offset + src_size as i64).min(dest_size as i64);
+    }
+
+    fn pixel(buffer: &BackBuffer, x: i32, y: i32) -> [u8; 4] {
+        let index = ((x + y * buffer.width) * BYTES_PER_PIXEL) as usize;
+        return [buffer[index], buffer[index + 1], buffer[index + 2], buffer[index + 3]];
+    }
+
+    // Checks every pixel, those the copy may write against source(x, y), the rest against the untouched value.
+    fn check(buffer: &BackBuffer, xs: &std::ops::Range<i64>, ys: &std::ops::Range<i64>, source: impl Fn(i64, i64) -> [u8; 4]) {
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let expected = if xs.contains(&(x as i64)) && ys.contains(&(y as i64)) { source(x as i64, y as i64) } else { [UNTOUCHED; 4] };
+                assert_eq!(pixel(buffer, x, y), expected, "pixel {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn clip_span_stays_inside_both_sizes() {
+        let mut lcg = Lcg(1);
+        for _ in 0..ROUNDS {
+            let (dest_size, src_size) = ((lcg.next() % 16) as i32 - 2, (lcg.next() % 16) as i32 - 2);
+            let (dest, src, length) = (lcg.coordinate(), lcg.coordinate(), lcg.coordinate());
+            let expected = expected_span(dest_size, src_size, dest, src as i64, length);
+
+            match clip_span(dest_size, src_size, dest, src, length) {
+                Some((clipped_dest, clipped_src, clipped_length)) => {
+                    assert!(clipped_length > 0 && clipped_dest >= 0 && clipped_src >= 0);
+                    assert!(clipped_dest + clipped_length <= dest_size && clipped_src + clipped_length <= src_size);
+                    assert_eq!(clipped_dest as i64 - clipped_src as i64, dest as i64 - src as i64);
+                    assert_eq!(clipped_dest as i64..clipped_dest as i64 + clipped_length as i64, expected);
+                }
+                None => assert!(expected.is_empty(), "{:?}", (dest_size, src_size, dest, src, length)),
+            }
+        }
+    }
+
+    #[test]
+    fn fill_rect_only_writes_inside_the_clipped_rect() {
+        let mut lcg = Lcg(2);
+        for _ in 0..ROUNDS {
+            let (x, y, width, height) = (lcg.coordinate(), lcg.coordinate(), lcg.coordinate(), lcg.coordinate());
+            let mut buffer = buffer();
+            buffer.fill_rect([1, 2, 3], x, y, width, height);
+
+            let xs = expected_span(WIDTH, width, x, 0, width);
+            let ys = expected_span(HEIGHT, height, y, 0, height);
+            check(&buffer, &xs, &ys, |_, _| [3, 2, 1, 255]);
+        }
+    }
+
+    #[test]
+    fn draw_subbitmap_only_writes_inside_the_clipped_rect() {
+        let (bitmap_width, bitmap_height) = (6, 4);
+        let bitmap = Bitmap { width: bitmap_width, height: bitmap_height, data: (0..bitmap_width * bitmap_height).map(|i| (i % 5) as u8).collect(), rgba: None };
+        let palette: Palette = (0..5).map(|i| [i * 10, i * 10 + 1, i * 10 + 2]).collect();
+
+        let mut lcg = Lcg(3);
+        for _ in 0..ROUNDS {
+            let (x_dest, y_dest, x_src, y_src, width, height) = (lcg.coordinate(), lcg.coordinate(), lcg.coordinate(), lcg.coordinate(), lcg.coordinate(), lcg.coordinate());
+            let mut buffer = buffer();
+            buffer.draw_subbitmap(&palette, &bitmap, x_dest, y_dest, x_src, y_src, width, height);
+
+            let xs = expected_span(WIDTH, bitmap_width, x_dest, x_src as i64, width);
+            let ys = expected_span(HEIGHT, bitmap_height, y_dest, y_src as i64, height);
+            check(&buffer, &xs, &ys, |x, y| {
+                let index = bitmap[((x - x_dest as i64 + x_src as i64) + (y - y_dest as i64 + y_src as i64) * bitmap_width as i64) as usize] as usize;
+                [palette[index][2], palette[index][1], palette[index][0], 255]
+            });
+        }
+    }
+
+    #[test]
+    fn draw_subbitmap_rgba_only_writes_inside_the_clipped_rect() {
+        let (pixels_per_row, rows) = (6, 4);
+        let pixels: Vec<Rgba> = (0..pixels_per_row * rows).map(|i| [i as u8, i as u8 + 100, i as u8 + 200, 255]).collect();
+
+        let mut lcg = Lcg(4);
+        for _ in 0..ROUNDS {
+            let (x_dest, y_dest, x_src, y_src, width, height) = (lcg.coordinate(), lcg.coordinate(), lcg.coordinate(), lcg.coordinate(), lcg.coordinate(), lcg.coordinate());
+            let mut buffer = buffer();
+            buffer.draw_subbitmap_rgba(&pixels, pixels_per_row, x_dest, y_dest, x_src, y_src, width, height);
+
+            // Opaque pixels replace whatever was there.
+            let xs = expected_span(WIDTH, pixels_per_row, x_dest, x_src as i64, width);
+            let ys = expected_span(HEIGHT, rows, y_dest, y_src as i64, height);
+            check(&buffer, &xs, &ys, |x, y| {
+                let [r, g, b, a] = pixels[((x - x_dest as i64 + x_src as i64) + (y - y_dest as i64 + y_src as i64) * pixels_per_row as i64) as usize];
+                [b, g, r, a]
+            });
+        }
+    }
+
+    fn palette() -> Palette {
+        return (0..5).map(|i| [i * 10, i * 10 + 1, i * 10 + 2]).collect();
+    }
+
+    // Sprites are 3x2, two to a row and two rows, so every index past 3 is off the sheet.
+    fn sheet() -> SpriteSheet {
+        let bitmap = Bitmap { width: 6, height: 4, data: (0..24).map(|i| (i % 5) as u8).collect(), rgba: None };
+        return SpriteSheet { bitmap: bitmap, sprite_width: 3, sprite_height: 2, sprites_per_row: 2 };
+    }
+
+    #[test]
+    fn draw_subsprite_only_writes_inside_the_clipped_rect() {
+        let (sheet, palette) = (sheet(), palette());
+
+        let mut lcg = Lcg(5);
+        for _ in 0..ROUNDS {
+            let index = if lcg.next() % 2 == 1 { lcg.coordinate() } else { (lcg.next() % 8) as i32 - 2 };
+            let (x_dest, y_dest, x_offs, y_offs, width, height) = (lcg.coordinate(), lcg.coordinate(), lcg.coordinate(), lcg.coordinate(), lcg.coordinate(), lcg.coordinate());
+            let mut buffer = buffer();
+            buffer.draw_subsprite(&palette, &sheet, x_dest, y_dest, index, x_offs, y_offs, width, height);
+
+            if index < 0 {
+                assert!(buffer.memory.iter().all(|byte| *byte == UNTOUCHED));
+                continue;
+            }
+
+            let x_src = (index % 2) as i64 * 3 + x_offs as i64;
+            let y_src = (index / 2) as i64 * 2 + y_offs as i64;
+            let xs = expected_span(WIDTH, 6, x_dest, x_src, width);
+            let ys = expected_span(HEIGHT, 4, y_dest, y_src, height);
+            check(&buffer, &xs, &ys, |x, y| {
+                let index = sheet.bitmap[((x - x_dest as i64 + x_src) + (y - y_dest as i64 + y_src) * 6) as usize] as usize;
+                [palette[index][2], palette[index][1], palette[index][0], 255]
+            });
+        }
+    }
+
+    #[test]
+    fn draw_text_only_writes_inside_its_glyphs() {
+        // Every glyph fits on a 16 sprite wide sheet of 2x3 sprites, and is 0, 1 or 2 pixels wide.
+        let rows = (GLYPH_COUNT + 15) / 16;
+        let bitmap = Bitmap { width: 32, height: rows * 3, data: (0..32 * rows * 3).map(|i| (i % 5) as u8).collect(), rgba: None };
+        let font = Font::new(SpriteSheet { bitmap: bitmap, sprite_width: 2, sprite_height: 3, sprites_per_row: 16 }, (0..GLYPH_COUNT).map(|glyph| glyph % 3).collect());
+        let palette = palette();
+        let characters = [' ', '!', '"', 'A', 'b', '~', '\u{e9}'];
+
+        let mut lcg = Lcg(6);
+        for _ in 0..ROUNDS {
+            let text: String = (0..lcg.next() % 6).map(|_| characters[lcg.next() as usize % characters.len()]).collect();
+            let (x_dest, y_dest) = (lcg.coordinate(), lcg.coordinate());
+            let mut buffer = buffer();
+            buffer.draw_text(&palette, &font, &text, x_dest, y_dest);
+
+            for y in 0..HEIGHT {
+                for x in 0..WIDTH {
+                    let mut expected = [UNTOUCHED; 4];
+                    let mut left = x_dest as i64;
+                    for character in text.chars() {
+                        let glyph = font.glyph(character);
+                        let width = font.widths[glyph as usize] as i64;
+                        let (column, row) = (x as i64 - left, y as i64 - y_dest as i64);
+                        if character != ' ' && (0..width).contains(&column) && (0..3).contains(&row) {
+                            let index = font.sheet.bitmap[((glyph % 16) as i64 * 2 + column + ((glyph / 16) as i64 * 3 + row) * 32) as usize] as usize;
+                            expected = [palette[index][2], palette[index][1], palette[index][0], 255];
+                        }
+                        left += width;
+                    }
+                    assert_eq!(pixel(&buffer, x, y), expected, "{:?} at {}, {}: pixel {}, {}", text, x_dest, y_dest, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rows_of_zero_or_negative_width_draw_nothing() {
+        let pixels: Vec<Rgba> = vec![[1, 2, 3, 255]; 8];
+        for pixels_per_row in [i32::MIN, -1, 0] {
+            let mut buffer = buffer();
+            buffer.draw_subbitmap_rgba(&pixels, pixels_per_row, 0, 0, 0, 0, WIDTH, HEIGHT);
+            assert!(buffer.memory.iter().all(|byte| *byte == UNTOUCHED));
+        }
+    }
 }
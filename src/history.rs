@@ -0,0 +1,209 @@
+use crate::application::*;
+use crate::gfx::*;
+
+use std::collections::*;
+use std::time::*;
+
+pub const DEFAULT_HISTORY_LENGTH: Duration = Duration::from_secs(4);
+pub const MAX_HISTORY_LENGTH: Duration = Duration::from_secs(60);
+pub const DEFAULT_HISTORY_SPEED: i32 = 40;
+pub const MAX_HISTORY_SPEED: i32 = 480;
+
+// However fast the panel scrolls, it is not redrawn more often than this.
+const MIN_SCROLL_INTERVAL: Duration = Duration::from_millis(16);
+const PANEL_MARGIN: i32 = 4;
+const ROW_SPACING: i32 = 4;
+const MIN_PRESS_WIDTH: i32 = 3;
+
+// One press of the key at that index of Application::keys. A key that is still held has no end yet.
+pub struct Press {
+    pub key: usize,
+    pub start: Duration,
+    pub end: Option<Duration>,
+}
+
+// The presses of the last `length`, oldest first. When visible they are drawn in a panel to the right of the controller,
+// one row per key, with the newest presses at the right edge scrolling to the left by `speed` pixels per second.
+pub struct InputHistory {
+    pub presses: VecDeque<Press>,
+    pub visible: bool,
+    pub length: Duration,
+    pub speed: i32,
+}
+
+impl InputHistory {
+    pub fn new(visible: bool, length: Duration, speed: i32) -> InputHistory {
+        return InputHistory { presses: VecDeque::new(), visible: visible, length: length, speed: speed };
+    }
+
+    // Key repeats of a held key are not presses of their own.
+    pub fn press(&mut self, key: usize, time: Duration) {
+        self.forget(time);
+        if !self.presses.iter().any(|press| press.key == key && press.end.is_none()) {
+            self.presses.push_back(Press { key: key, start: time, end: None });
+        }
+    }
+
+    pub fn release(&mut self, key: usize, time: Duration) {
+        if let Some(press) = self.presses.iter_mut().find(|press| press.key == key && press.end.is_none()) {
+            press.end = Some(time);
+        }
+        self.forget(time);
+    }
+
+    // Drops the presses that ended before the history begins.
+    pub fn forget(&mut self, now: Duration) {
+        let length = self.length;
+        self.presses.retain(|press| press.end.is_none_or(|end| end + length >= now));
+    }
+
+    // The panel keeps scrolling until the last press has left it.
+    pub fn is_scrolling(&self) -> bool {
+        return self.visible && !self.presses.is_empty();
+    }
+
+    pub fn scroll_interval(&self) -> Duration {
+        return Duration::from_secs_f32(1.0 / self.speed as f32).max(MIN_SCROLL_INTERVAL);
+    }
+
+    fn lane_width(&self) -> i32 {
+        return (self.length.as_secs_f32() * self.speed as f32).ceil() as i32;
+    }
+
+    // The key names on the left, then a lane as long as the history at the scroll speed. Nothing when hidden.
+    pub fn panel_size(&self, font: &Font, names: &[&str]) -> (i32, i32) {
+        if !self.visible {
+            return (0, 0);
+        }
+
+        let label_width = names.iter().map(|name| font.text_width(name)).max().unwrap_or(0);
+        let row_height = font.sheet.sprite_height + ROW_SPACING;
+        return (label_width + self.lane_width() + PANEL_MARGIN * 3, names.len() as i32 * row_height + PANEL_MARGIN * 2);
+    }
+}
+
+fn key_names(app: &Application) -> Vec<&str> {
+    return app.keys.iter().map(|key| key.name.as_str()).collect();
+}
+
+pub fn history_panel_width(app: &Application) -> i32 {
+    return app.history.panel_size(&app.font, &key_names(app)).0;
+}
+
+pub fn record_key_state(app: &mut Application, key: usize, pressed: bool) {
+    let now = app.platform.time();
+    if pressed {
+        app.history.press(key, now);
+    } else {
+        app.history.release(key, now);
+    }
+}
+
+pub fn draw_history(app: &mut Application) {
+    if !app.history.visible {
+        return;
+    }
+
+    let now = app.platform.time();
+    app.history.forget(now);
+
+    let panel_width = history_panel_width(app);
+    let speed = app.history.speed as f32;
+    let font_height = app.font.sheet.sprite_height;
+    let row_height = font_height + ROW_SPACING;
+    let lane_right = app.offscreen_buffer.width - PANEL_MARGIN;
+    let lane_left = lane_right - app.history.lane_width();
+    let row_y = |key: usize| PANEL_MARGIN + key as i32 * row_height + ROW_SPACING / 2;
+    let position = |time: Duration| lane_right - (now.saturating_sub(time).as_secs_f32() * speed) as i32;
+
+    for (i, key) in app.keys.iter().enumerate() {
+        app.offscreen_buffer.draw_text(&app.palette, &app.font, &key.name, app.offscreen_buffer.width - panel_width + PANEL_MARGIN, row_y(i));
+    }
+    app.offscreen_buffer.fill_rect(app.palette[0], lane_left - PANEL_MARGIN / 2, PANEL_MARGIN, 1, app.keys.len() as i32 * row_height);
+
+    for press in app.history.presses.iter() {
+        let start = position(press.start).max(lane_left);
+        let end = press.end.map_or(lane_right, position).max(start + MIN_PRESS_WIDTH);
+        app.offscreen_buffer.fill_rect(app.palette[0], start, row_y(press.key), end - start, font_height);
+        app.offscreen_buffer.fill_rect(app.palette[1], start + 1, row_y(press.key) + 1, end - start - 2, font_height - 2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bmp::*;
+
+    fn at(milliseconds: u64) -> Duration {
+        return Duration::from_millis(milliseconds);
+    }
+
+    fn presses(history: &InputHistory) -> Vec<(usize, Duration, Option<Duration>)> {
+        return history.presses.iter().map(|press| (press.key, press.start, press.end)).collect();
+    }
+
+    #[test]
+    fn repeats_of_a_held_key_are_one_press() {
+        let mut history = InputHistory::new(true, at(1000), DEFAULT_HISTORY_SPEED);
+        history.press(0, at(0));
+        history.press(0, at(100));
+        history.press(1, at(200));
+        history.release(0, at(300));
+        history.release(0, at(400));
+        history.press(0, at(500));
+        assert_eq!(presses(&history), vec![(0, at(0), Some(at(300))), (1, at(200), None), (0, at(500), None)]);
+    }
+
+    #[test]
+    fn presses_older_than_the_history_are_forgotten() {
+        let mut history = InputHistory::new(true, at(1000), DEFAULT_HISTORY_SPEED);
+        for i in 0..10 {
+            history.press(i % 2, at(i as u64 * 300));
+            history.release(i % 2, at(i as u64 * 300 + 100));
+        }
+
+        // The last press ended at 2800, the one before at 2500 and so on. A press stays until the history has scrolled past its end.
+        history.forget(at(3500));
+        assert_eq!(presses(&history), vec![(0, at(2400), Some(at(2500))), (1, at(2700), Some(at(2800)))]);
+        history.forget(at(3501));
+        assert_eq!(presses(&history), vec![(1, at(2700), Some(at(2800)))]);
+        history.forget(at(3800));
+        assert_eq!(presses(&history), vec![(1, at(2700), Some(at(2800)))]);
+        history.forget(at(3801));
+        assert!(presses(&history).is_empty());
+        assert!(!history.is_scrolling());
+    }
+
+    #[test]
+    fn held_keys_are_not_forgotten() {
+        let mut history = InputHistory::new(true, at(1000), DEFAULT_HISTORY_SPEED);
+        history.press(3, at(0));
+        history.press(4, at(10));
+        history.release(4, at(20));
+        history.forget(at(60000));
+        assert_eq!(presses(&history), vec![(3, at(0), None)]);
+        assert!(history.is_scrolling());
+
+        history.release(3, at(60000));
+        history.forget(at(61000));
+        assert_eq!(presses(&history), vec![(3, at(0), Some(at(60000)))]);
+        history.forget(at(61001));
+        assert!(presses(&history).is_empty());
+    }
+
+    #[test]
+    fn panels_fit_the_names_and_the_lane() {
+        // 5 pixel high glyphs, each as wide as its index modulo 4, which makes "SELECT" 3 + 1 + 0 + 1 + 3 + 0 pixels wide.
+        let rows = (GLYPH_COUNT + 15) / 16;
+        let bitmap = Bitmap { width: 64, height: rows * 5, data: vec![0; (64 * rows * 5) as usize], rgba: None };
+        let font = Font::new(SpriteSheet { bitmap: bitmap, sprite_width: 4, sprite_height: 5, sprites_per_row: 16 }, (0..GLYPH_COUNT).map(|glyph| glyph % 4).collect());
+        let names = ["A", "SELECT", "UP"];
+        let label_width = font.text_width("SELECT");
+        assert_eq!(label_width, 8);
+
+        assert_eq!(InputHistory::new(false, at(4000), 40).panel_size(&font, &names), (0, 0));
+        assert_eq!(InputHistory::new(true, at(4000), 40).panel_size(&font, &names), (label_width + 160 + 12, 3 * 9 + 8));
+        assert_eq!(InputHistory::new(true, at(1010), 100).panel_size(&font, &names), (label_width + 101 + 12, 3 * 9 + 8));
+        assert_eq!(InputHistory::new(true, at(4000), 40).panel_size(&font, &[]), (160 + 12, 8));
+    }
+}
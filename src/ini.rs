@@ -0,0 +1,147 @@
+use std::collections::*;
+use std::fmt;
+
+// Minimal INI document. Sections and keys keep the order they were read or added in, so files round-trip predictably.
+// Lines starting with ';' or '#' are comments. They are kept, as are blank lines, together with the section header or entry
+// that follows them, and written back in front of it. An entry that goes away takes its comments with it.
+#[derive(Default)]
+pub struct Ini {
+    pub sections: Vec<(String, Vec<(String, String)>)>,
+    // Keyed by section and entry, the entry is empty for the section header.
+    comments: HashMap<(String, String), Vec<String>>,
+    trailing_comments: Vec<String>,
+}
+
+impl Ini {
+    pub fn parse(text: &str) -> Ini {
+        let mut ini = Ini::default();
+        let mut section = String::new();
+        let mut comments: Vec<String> = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                comments.push(line.to_string());
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].trim().to_string();
+                ini.add_comments(&section, "", &mut comments);
+                continue;
+            }
+
+            if let Some(separator) = line.find('=') {
+                let key = line[..separator].trim();
+                ini.set(&section, key, line[separator + 1..].trim());
+                ini.add_comments(&section, key, &mut comments);
+            }
+        }
+
+        ini.trailing_comments = comments;
+        return ini;
+    }
+
+    fn add_comments(&mut self, section: &str, key: &str, comments: &mut Vec<String>) {
+        if !comments.is_empty() {
+            self.comments.entry((section.to_string(), key.to_string())).or_default().append(comments);
+        }
+    }
+
+    pub fn section(&self, name: &str) -> Option<&[(String, String)]> {
+        return self.sections.iter().find(|section| section.0 == name).map(|section| section.1.as_slice());
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        return self.section(section)?.iter().find(|entry| entry.0 == key).map(|entry| entry.1.as_str());
+    }
+
+    pub fn remove_section(&mut self, name: &str) {
+        self.sections.retain(|section| section.0 != name);
+    }
+
+    pub fn set(&mut self, section: &str, key: &str, value: &str) {
+        let index = match self.sections.iter().position(|entry| entry.0 == section) {
+            Some(index) => index,
+            None => {
+                self.sections.push((section.to_string(), Vec::new()));
+                self.sections.len() - 1
+            }
+        };
+
+        let entries = &mut self.sections[index].1;
+        match entries.iter_mut().find(|entry| entry.0 == key) {
+            Some(entry) => entry.1 = value.to_string(),
+            None => entries.push((key.to_string(), value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Ini {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let write_comments = |f: &mut fmt::Formatter, lines: &[String]| lines.iter().try_for_each(|line| writeln!(f, "{}", line));
+
+        for (i, (name, entries)) in self.sections.iter().enumerate() {
+            // Sections that were read keep the lines they had in front of them, new ones are set apart by a blank line.
+            match self.comments.get(&(name.clone(), String::new())) {
+                Some(lines) => write_comments(f, lines)?,
+                None if i > 0 => writeln!(f)?,
+                None => {}
+            }
+
+            if !name.is_empty() {
+                writeln!(f, "[{}]", name)?;
+            }
+
+            for (key, value) in entries.iter() {
+                if let Some(lines) = self.comments.get(&(name.clone(), key.clone())) {
+                    write_comments(f, lines)?;
+                }
+                writeln!(f, "{} = {}", key, value)?;
+            }
+        }
+
+        write_comments(f, &self.trailing_comments)?;
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "; Written by hand\n\n[keys]\n# Arrows\nUP = UP\nA = Z, X\n\n; Shown on start\n[display]\npalette = Green\n\n; The end\n";
+
+    #[test]
+    fn comments_and_blank_lines_round_trip() {
+        let ini = Ini::parse(TEXT);
+        assert_eq!(ini.get("keys", "A"), Some("Z, X"));
+        assert_eq!(ini.to_string(), TEXT);
+        assert_eq!(Ini::parse(&ini.to_string()).to_string(), TEXT);
+    }
+
+    #[test]
+    fn comments_survive_changes() {
+        let mut ini = Ini::parse(TEXT);
+        ini.set("keys", "A", "C");
+        ini.set("display", "scale", "2");
+        ini.set("palettes", "Pocket", "#000000, #8bac0f, #306230, #9bbc0f");
+
+        let expected = "; Written by hand\n\n[keys]\n# Arrows\nUP = UP\nA = C\n\n; Shown on start\n[display]\npalette = Green\nscale = 2\n\n[palettes]\nPocket = #000000, #8bac0f, #306230, #9bbc0f\n\n; The end\n";
+        assert_eq!(ini.to_string(), expected);
+    }
+
+    #[test]
+    fn removed_sections_lose_their_entries() {
+        let mut ini = Ini::parse(TEXT);
+        ini.remove_section("keys");
+        assert_eq!(ini.to_string(), "\n; Shown on start\n[display]\npalette = Green\n\n; The end\n");
+    }
+
+    #[test]
+    fn sections_without_blank_lines_get_one() {
+        let ini = Ini::parse("[a]\nx = 1\n[b]\ny = 2\n");
+        assert_eq!(ini.to_string(), "[a]\nx = 1\n\n[b]\ny = 2\n");
+    }
+}
@@ -0,0 +1,180 @@
+use crate::application::*;
+use crate::gfx::*;
+use crate::history::*;
+
+use std::collections::*;
+use std::time::*;
+
+pub const DEFAULT_INPUT_LOG_LENGTH: usize = 8;
+pub const MAX_INPUT_LOG_LENGTH: usize = 32;
+
+// Hold times are counted in frames of the Game Boy, which runs at 4194304 / 70224 frames per second.
+const FRAME_RATE: f64 = 4194304.0 / 70224.0;
+const SEPARATOR: &str = ", ";
+
+// The last `length` presses, oldest first. When visible they are written in the text line whenever no notification is shown,
+// e.g. "A 3f, UP 12f, B 2f", with the count of a key that is still held going up as long as it is.
+pub struct InputLog {
+    pub presses: VecDeque<Press>,
+    pub visible: bool,
+    pub length: usize,
+}
+
+pub fn frames(duration: Duration) -> u64 {
+    return (duration.as_secs_f64() * FRAME_RATE).round() as u64;
+}
+
+pub fn frame_duration() -> Duration {
+    return Duration::from_secs_f64(1.0 / FRAME_RATE);
+}
+
+impl InputLog {
+    pub fn new(visible: bool, length: usize) -> InputLog {
+        return InputLog { presses: VecDeque::new(), visible: visible, length: length };
+    }
+
+    // Key repeats of a held key are not presses of their own.
+    pub fn press(&mut self, key: usize, time: Duration) {
+        if self.presses.iter().any(|press| press.key == key && press.end.is_none()) {
+            return;
+        }
+
+        self.presses.push_back(Press { key: key, start: time, end: None });
+        while self.presses.len() > self.length {
+            self.presses.pop_front();
+        }
+    }
+
+    pub fn release(&mut self, key: usize, time: Duration) {
+        if let Some(press) = self.presses.iter_mut().find(|press| press.key == key && press.end.is_none()) {
+            press.end = Some(time);
+        }
+    }
+
+    // Held keys are recounted every frame.
+    pub fn is_counting(&self) -> bool {
+        return self.visible && self.presses.iter().any(|press| press.end.is_none());
+    }
+
+    // As many of the newest presses as fit in max_width.
+    pub fn text(&self, font: &Font, names: &[&str], now: Duration, max_width: i32) -> String {
+        let mut text = String::new();
+
+        for press in self.presses.iter().rev() {
+            let entry = format!("{} {}f", names[press.key], frames(press.end.unwrap_or(now).saturating_sub(press.start)));
+            let line = if text.is_empty() { entry } else { format!("{}{}{}", entry, SEPARATOR, text) };
+            if font.text_width(&line) > max_width {
+                break;
+            }
+            text = line;
+        }
+
+        return text;
+    }
+}
+
+pub fn log_key_state(app: &mut Application, key: usize, pressed: bool) {
+    let now = app.platform.time();
+    if pressed {
+        app.input_log.press(key, now);
+    } else {
+        app.input_log.release(key, now);
+    }
+}
+
+pub fn input_log_text(app: &Application, max_width: i32) -> String {
+    let names: Vec<&str> = app.keys.iter().map(|key| key.name.as_str()).collect();
+    return app.input_log.text(&app.font, &names, app.platform.time(), max_width);
+}
+
+pub fn toggle_input_log(app: &mut Application) {
+    app.input_log.visible = !app.input_log.visible;
+    update_menu(app);
+    draw_background(app);
+    arm_timer(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bmp::*;
+
+    // Every glyph is one pixel wide, so a line is as wide as it is long.
+    fn font() -> Font {
+        let rows = (GLYPH_COUNT + 15) / 16;
+        let bitmap = Bitmap { width: 16, height: rows, data: vec![0; (16 * rows) as usize], rgba: None };
+        return Font::new(SpriteSheet { bitmap: bitmap, sprite_width: 1, sprite_height: 1, sprites_per_row: 16 }, Vec::new());
+    }
+
+    const NAMES: &[&str] = &["A", "B", "UP"];
+
+    fn frame_times(count: f64) -> Duration {
+        return frame_duration().mul_f64(count);
+    }
+
+    #[test]
+    fn frames_are_rounded_to_the_nearest() {
+        assert_eq!(frames(Duration::ZERO), 0);
+        assert_eq!(frames(frame_times(0.49)), 0);
+        assert_eq!(frames(frame_times(0.51)), 1);
+        assert_eq!(frames(frame_duration()), 1);
+        assert_eq!(frames(frame_times(1.49)), 1);
+        assert_eq!(frames(frame_times(1.51)), 2);
+        assert_eq!(frames(frame_times(600.0)), 600);
+        // 59.73 frames.
+        assert_eq!(frames(Duration::from_secs(1)), 60);
+    }
+
+    #[test]
+    fn only_the_last_presses_are_kept() {
+        let mut log = InputLog::new(true, 3);
+        for key in [0, 1, 2, 0, 1] {
+            log.press(key, frame_times(key as f64));
+            log.release(key, frame_times(key as f64 + 2.0));
+        }
+        assert_eq!(log.presses.iter().map(|press| press.key).collect::<Vec<usize>>(), vec![2, 0, 1]);
+
+        // Repeats of a held key do not push anything out.
+        log.press(2, frame_times(10.0));
+        log.press(2, frame_times(11.0));
+        assert_eq!(log.presses.iter().map(|press| press.key).collect::<Vec<usize>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn held_keys_count_up_to_now() {
+        let font = font();
+        let mut log = InputLog::new(true, 8);
+        log.press(0, frame_times(0.0));
+        log.release(0, frame_times(3.0));
+        log.press(2, frame_times(5.0));
+        assert!(log.is_counting());
+
+        assert_eq!(log.text(&font, NAMES, frame_times(5.0), 100), "A 3f, UP 0f");
+        assert_eq!(log.text(&font, NAMES, frame_times(17.4), 100), "A 3f, UP 12f");
+        assert_eq!(log.text(&font, NAMES, frame_times(17.6), 100), "A 3f, UP 13f");
+
+        log.release(2, frame_times(20.0));
+        assert!(!log.is_counting());
+        assert_eq!(log.text(&font, NAMES, frame_times(90.0), 100), "A 3f, UP 15f");
+        assert!(!InputLog::new(false, 8).is_counting());
+    }
+
+    #[test]
+    fn the_oldest_presses_are_left_out_when_the_line_is_full() {
+        let font = font();
+        let mut log = InputLog::new(true, 8);
+        log.press(0, frame_times(0.0));
+        log.release(0, frame_times(3.0));
+        log.press(1, frame_times(4.0));
+        log.release(1, frame_times(6.0));
+        log.press(2, frame_times(7.0));
+
+        let now = frame_times(19.0);
+        assert_eq!(log.text(&font, NAMES, now, 18), "A 3f, B 2f, UP 12f");
+        assert_eq!(log.text(&font, NAMES, now, 17), "B 2f, UP 12f");
+        assert_eq!(log.text(&font, NAMES, now, 12), "B 2f, UP 12f");
+        assert_eq!(log.text(&font, NAMES, now, 11), "UP 12f");
+        assert_eq!(log.text(&font, NAMES, now, 6), "UP 12f");
+        assert_eq!(log.text(&font, NAMES, now, 5), "");
+    }
+}
@@ -1,15 +1,20 @@
+use crate::keymap::*;
+use crate::stats::*;
+
 pub const KEY_PRESSED_BIT: i32 = 16;
 pub const KEY_CONTRACTED_BIT: i32 = 32;
 pub const KEY_ELONGATED_BIT: i32 = 64;
 
 pub struct Key {
-    pub primary_ipt: u32,
-    pub secondary_ipt: u32,
+    pub primary_ipt: KeyCode,
+    pub secondary_ipt: KeyCode,
     pub x: f32,
     pub y: f32,
+    pub count: Option<(f32, f32)>,
     pub idx: i32,
     pub name: String,
     pub reg_entry: String,
+    pub stats: KeyStats,
 }
 
 impl Key {
@@ -0,0 +1,432 @@
+use std::fmt;
+
+// Keys as the rest of the program sees them, independent of the platform they were read on.
+// Virtual-key codes that have no name of their own are kept as Unknown, so nothing is lost in a round trip.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum KeyCode {
+    None,
+    Cancel,
+    Backspace,
+    Tab,
+    Clear,
+    Enter,
+    Pause,
+    CapsLock,
+    Escape,
+    ModeChange,
+    Space,
+    PageUp,
+    PageDown,
+    End,
+    Home,
+    Left,
+    Up,
+    Right,
+    Down,
+    Select,
+    Print,
+    Execute,
+    PrintScreen,
+    Insert,
+    Delete,
+    Help,
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    LWin,
+    RWin,
+    Menu,
+    Sleep,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    Multiply,
+    Add,
+    Subtract,
+    Decimal,
+    Divide,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    NumLock,
+    ScrollLock,
+    Jisho,
+    Masshou,
+    Touroku,
+    Loya,
+    Roya,
+    LShift,
+    RShift,
+    LControl,
+    RControl,
+    LAlt,
+    RAlt,
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    MediaNext,
+    MediaPrevious,
+    MediaStop,
+    MediaPlayPause,
+    LaunchMail,
+    LaunchMedia,
+    LaunchApp1,
+    LaunchApp2,
+    Semicolon,
+    Equals,
+    Comma,
+    Minus,
+    Period,
+    Slash,
+    Grave,
+    LeftBracket,
+    Backslash,
+    RightBracket,
+    Apostrophe,
+    Oem8,
+    Oem102,
+    Play,
+    Zoom,
+    Unknown(u32),
+}
+
+// One row per key: its display name, the Windows virtual-key code, the Qt key code Gambatte stores,
+// the X11 keysym and the Linux evdev code for the same key. A zero means the key has no code in that column.
+// Keys with more than one code on a side (e.g. both Enter keys) get extra rows for the aliases; lookups towards
+// a KeyCode accept any of them, lookups away from it return the first.
+const KEY_CODES: &[(KeyCode, &str, u32, u32, u32, u16)] = &[
+    (KeyCode::Cancel, "CANCEL", 0x03, 0x01020001, 0x0000, 0),
+    (KeyCode::Backspace, "BACKSPACE", 0x08, 0x01000003, 0xFF08, 14),
+    (KeyCode::Tab, "TAB", 0x09, 0x01000001, 0xFF09, 15),
+    (KeyCode::Clear, "CLEAR", 0x0C, 0x0100000B, 0x0000, 0),
+    (KeyCode::Enter, "ENTER", 0x0D, 0x01000004, 0xFF0D, 28),
+    (KeyCode::Enter, "ENTER", 0x0D, 0x00000000, 0x0000, 96),
+    (KeyCode::Pause, "PAUSE", 0x13, 0x01000008, 0xFF13, 119),
+    (KeyCode::CapsLock, "CAPSLOCK", 0x14, 0x01000024, 0xFFE5, 58),
+    (KeyCode::Escape, "ESCAPE", 0x1B, 0x01000000, 0xFF1B, 1),
+    (KeyCode::ModeChange, "MODECHANGE", 0x1F, 0x0100117E, 0x0000, 0),
+    (KeyCode::Space, "SPACE", 0x20, 0x00000020, 0x0020, 57),
+    (KeyCode::PageUp, "PAGEUP", 0x21, 0x01000016, 0xFF55, 104),
+    (KeyCode::PageDown, "PAGEDOWN", 0x22, 0x01000017, 0xFF56, 109),
+    (KeyCode::End, "END", 0x23, 0x01000011, 0xFF57, 107),
+    (KeyCode::Home, "HOME", 0x24, 0x01000010, 0xFF50, 102),
+    (KeyCode::Left, "LEFT", 0x25, 0x01000012, 0xFF51, 105),
+    (KeyCode::Up, "UP", 0x26, 0x01000013, 0xFF52, 103),
+    (KeyCode::Right, "RIGHT", 0x27, 0x01000014, 0xFF53, 106),
+    (KeyCode::Down, "DOWN", 0x28, 0x01000015, 0xFF54, 108),
+    (KeyCode::Select, "SELECT", 0x29, 0x01010000, 0x0000, 0),
+    (KeyCode::Print, "PRINT", 0x2A, 0x01020002, 0x0000, 0),
+    (KeyCode::Execute, "EXECUTE", 0x2B, 0x01020003, 0x0000, 0),
+    (KeyCode::PrintScreen, "PRINTSCREEN", 0x2C, 0x01000009, 0xFF61, 99),
+    (KeyCode::Insert, "INSERT", 0x2D, 0x01000006, 0xFF63, 110),
+    (KeyCode::Delete, "DELETE", 0x2E, 0x01000007, 0xFFFF, 111),
+    (KeyCode::Help, "HELP", 0x2F, 0x01000058, 0x0000, 0),
+    (KeyCode::Digit0, "0", 0x30, 0x00000030, 0x0030, 11),
+    (KeyCode::Digit1, "1", 0x31, 0x00000031, 0x0031, 2),
+    (KeyCode::Digit2, "2", 0x32, 0x00000032, 0x0032, 3),
+    (KeyCode::Digit3, "3", 0x33, 0x00000033, 0x0033, 4),
+    (KeyCode::Digit4, "4", 0x34, 0x00000034, 0x0034, 5),
+    (KeyCode::Digit5, "5", 0x35, 0x00000035, 0x0035, 6),
+    (KeyCode::Digit6, "6", 0x36, 0x00000036, 0x0036, 7),
+    (KeyCode::Digit7, "7", 0x37, 0x00000037, 0x0037, 8),
+    (KeyCode::Digit8, "8", 0x38, 0x00000038, 0x0038, 9),
+    (KeyCode::Digit9, "9", 0x39, 0x00000039, 0x0039, 10),
+    (KeyCode::A, "A", 0x41, 0x00000041, 0x0061, 30),
+    (KeyCode::B, "B", 0x42, 0x00000042, 0x0062, 48),
+    (KeyCode::C, "C", 0x43, 0x00000043, 0x0063, 46),
+    (KeyCode::D, "D", 0x44, 0x00000044, 0x0064, 32),
+    (KeyCode::E, "E", 0x45, 0x00000045, 0x0065, 18),
+    (KeyCode::F, "F", 0x46, 0x00000046, 0x0066, 33),
+    (KeyCode::G, "G", 0x47, 0x00000047, 0x0067, 34),
+    (KeyCode::H, "H", 0x48, 0x00000048, 0x0068, 35),
+    (KeyCode::I, "I", 0x49, 0x00000049, 0x0069, 23),
+    (KeyCode::J, "J", 0x4A, 0x0000004A, 0x006A, 36),
+    (KeyCode::K, "K", 0x4B, 0x0000004B, 0x006B, 37),
+    (KeyCode::L, "L", 0x4C, 0x0000004C, 0x006C, 38),
+    (KeyCode::M, "M", 0x4D, 0x0000004D, 0x006D, 50),
+    (KeyCode::N, "N", 0x4E, 0x0000004E, 0x006E, 49),
+    (KeyCode::O, "O", 0x4F, 0x0000004F, 0x006F, 24),
+    (KeyCode::P, "P", 0x50, 0x00000050, 0x0070, 25),
+    (KeyCode::Q, "Q", 0x51, 0x00000051, 0x0071, 16),
+    (KeyCode::R, "R", 0x52, 0x00000052, 0x0072, 19),
+    (KeyCode::S, "S", 0x53, 0x00000053, 0x0073, 31),
+    (KeyCode::T, "T", 0x54, 0x00000054, 0x0074, 20),
+    (KeyCode::U, "U", 0x55, 0x00000055, 0x0075, 22),
+    (KeyCode::V, "V", 0x56, 0x00000056, 0x0076, 47),
+    (KeyCode::W, "W", 0x57, 0x00000057, 0x0077, 17),
+    (KeyCode::X, "X", 0x58, 0x00000058, 0x0078, 45),
+    (KeyCode::Y, "Y", 0x59, 0x00000059, 0x0079, 21),
+    (KeyCode::Z, "Z", 0x5A, 0x0000005A, 0x007A, 44),
+    (KeyCode::LWin, "LWIN", 0x5B, 0x01000022, 0xFFEB, 125),
+    (KeyCode::RWin, "RWIN", 0x5C, 0x00000000, 0xFFEC, 126),
+    (KeyCode::Menu, "MENU", 0x5D, 0x01000055, 0xFF67, 127),
+    (KeyCode::Sleep, "SLEEP", 0x5F, 0x01020004, 0x0000, 0),
+    (KeyCode::Numpad0, "NUMPAD0", 0x60, 0x00000000, 0xFFB0, 82),
+    (KeyCode::Numpad1, "NUMPAD1", 0x61, 0x00000000, 0xFFB1, 79),
+    (KeyCode::Numpad2, "NUMPAD2", 0x62, 0x00000000, 0xFFB2, 80),
+    (KeyCode::Numpad3, "NUMPAD3", 0x63, 0x00000000, 0xFFB3, 81),
+    (KeyCode::Numpad4, "NUMPAD4", 0x64, 0x00000000, 0xFFB4, 75),
+    (KeyCode::Numpad5, "NUMPAD5", 0x65, 0x00000000, 0xFFB5, 76),
+    (KeyCode::Numpad6, "NUMPAD6", 0x66, 0x00000000, 0xFFB6, 77),
+    (KeyCode::Numpad7, "NUMPAD7", 0x67, 0x00000000, 0xFFB7, 71),
+    (KeyCode::Numpad8, "NUMPAD8", 0x68, 0x00000000, 0xFFB8, 72),
+    (KeyCode::Numpad9, "NUMPAD9", 0x69, 0x00000000, 0xFFB9, 73),
+    (KeyCode::Multiply, "MULTIPLY", 0x6A, 0x0000002A, 0xFFAA, 55),
+    (KeyCode::Add, "ADD", 0x6B, 0x0000002B, 0xFFAB, 78),
+    (KeyCode::Subtract, "SUBTRACT", 0x6D, 0x00000000, 0xFFAD, 74),
+    (KeyCode::Decimal, "DECIMAL", 0x6E, 0x00000000, 0xFFAE, 83),
+    (KeyCode::Divide, "DIVIDE", 0x6F, 0x00000000, 0xFFAF, 98),
+    (KeyCode::F1, "F1", 0x70, 0x01000030, 0xFFBE, 59),
+    (KeyCode::F2, "F2", 0x71, 0x01000031, 0xFFBF, 60),
+    (KeyCode::F3, "F3", 0x72, 0x01000032, 0xFFC0, 61),
+    (KeyCode::F4, "F4", 0x73, 0x01000033, 0xFFC1, 62),
+    (KeyCode::F5, "F5", 0x74, 0x01000034, 0xFFC2, 63),
+    (KeyCode::F6, "F6", 0x75, 0x01000035, 0xFFC3, 64),
+    (KeyCode::F7, "F7", 0x76, 0x01000036, 0xFFC4, 65),
+    (KeyCode::F8, "F8", 0x77, 0x01000037, 0xFFC5, 66),
+    (KeyCode::F9, "F9", 0x78, 0x01000038, 0xFFC6, 67),
+    (KeyCode::F10, "F10", 0x79, 0x01000039, 0xFFC7, 68),
+    (KeyCode::F11, "F11", 0x7A, 0x0100003A, 0xFFC8, 87),
+    (KeyCode::F12, "F12", 0x7B, 0x0100003B, 0xFFC9, 88),
+    (KeyCode::F13, "F13", 0x7C, 0x0100003C, 0xFFCA, 183),
+    (KeyCode::F14, "F14", 0x7D, 0x0100003D, 0xFFCB, 184),
+    (KeyCode::F15, "F15", 0x7E, 0x0100003E, 0xFFCC, 185),
+    (KeyCode::F16, "F16", 0x7F, 0x0100003F, 0xFFCD, 186),
+    (KeyCode::F17, "F17", 0x80, 0x01000040, 0xFFCE, 187),
+    (KeyCode::F18, "F18", 0x81, 0x01000041, 0xFFCF, 188),
+    (KeyCode::F19, "F19", 0x82, 0x01000042, 0xFFD0, 189),
+    (KeyCode::F20, "F20", 0x83, 0x01000043, 0xFFD1, 190),
+    (KeyCode::F21, "F21", 0x84, 0x01000044, 0xFFD2, 191),
+    (KeyCode::F22, "F22", 0x85, 0x01000045, 0xFFD3, 192),
+    (KeyCode::F23, "F23", 0x86, 0x01000046, 0xFFD4, 193),
+    (KeyCode::F24, "F24", 0x87, 0x01000047, 0xFFD5, 194),
+    (KeyCode::NumLock, "NUMLOCK", 0x90, 0x01000025, 0xFF7F, 69),
+    (KeyCode::ScrollLock, "SCROLLLOCK", 0x91, 0x01000026, 0xFF14, 70),
+    (KeyCode::Jisho, "JISHO", 0x92, 0x00000092, 0x0000, 0),
+    (KeyCode::Masshou, "MASSHOU", 0x93, 0x0100112C, 0x0000, 0),
+    (KeyCode::Touroku, "TOUROKU", 0x94, 0x0100112B, 0x0000, 0),
+    (KeyCode::Loya, "LOYA", 0x95, 0x00000095, 0x0000, 0),
+    (KeyCode::Roya, "ROYA", 0x96, 0x00000096, 0x0000, 0),
+    (KeyCode::LShift, "LSHIFT", 0xA0, 0x01000020, 0xFFE1, 42),
+    (KeyCode::RShift, "RSHIFT", 0xA1, 0x00000000, 0xFFE2, 54),
+    (KeyCode::LControl, "LCONTROL", 0xA2, 0x01000021, 0xFFE3, 29),
+    (KeyCode::RControl, "RCONTROL", 0xA3, 0x00000000, 0xFFE4, 97),
+    (KeyCode::LAlt, "LALT", 0xA4, 0x01000023, 0xFFE9, 56),
+    (KeyCode::RAlt, "RALT", 0xA5, 0x00000000, 0xFFEA, 100),
+    (KeyCode::BrowserBack, "BROWSERBACK", 0xA6, 0x01000061, 0x0000, 0),
+    (KeyCode::BrowserForward, "BROWSERFORWARD", 0xA7, 0x01000062, 0x0000, 0),
+    (KeyCode::BrowserRefresh, "BROWSERREFRESH", 0xA8, 0x01000064, 0x0000, 0),
+    (KeyCode::BrowserStop, "BROWSERSTOP", 0xA9, 0x01000063, 0x0000, 0),
+    (KeyCode::BrowserSearch, "BROWSERSEARCH", 0xAA, 0x01000092, 0x0000, 0),
+    (KeyCode::BrowserFavorites, "BROWSERFAVORITES", 0xAB, 0x01000091, 0x0000, 0),
+    (KeyCode::BrowserHome, "BROWSERHOME", 0xAC, 0x01000090, 0x0000, 0),
+    (KeyCode::VolumeMute, "VOLUMEMUTE", 0xAD, 0x01000071, 0x0000, 0),
+    (KeyCode::VolumeDown, "VOLUMEDOWN", 0xAE, 0x01000070, 0x0000, 0),
+    (KeyCode::VolumeUp, "VOLUMEUP", 0xAF, 0x01000072, 0x0000, 0),
+    (KeyCode::MediaNext, "MEDIANEXT", 0xB0, 0x01000083, 0x0000, 0),
+    (KeyCode::MediaPrevious, "MEDIAPREVIOUS", 0xB1, 0x01000082, 0x0000, 0),
+    (KeyCode::MediaStop, "MEDIASTOP", 0xB2, 0x01000081, 0x0000, 0),
+    (KeyCode::MediaPlayPause, "MEDIAPLAYPAUSE", 0xB3, 0x01000080, 0x0000, 0),
+    (KeyCode::LaunchMail, "LAUNCHMAIL", 0xB4, 0x010000A0, 0x0000, 0),
+    (KeyCode::LaunchMedia, "LAUNCHMEDIA", 0xB5, 0x010000A1, 0x0000, 0),
+    (KeyCode::LaunchApp1, "LAUNCHAPP1", 0xB6, 0x010000A2, 0x0000, 0),
+    (KeyCode::LaunchApp2, "LAUNCHAPP2", 0xB7, 0x010000A3, 0x0000, 0),
+    (KeyCode::Semicolon, "SEMICOLON", 0xBA, 0x000000BA, 0x003B, 39),
+    (KeyCode::Semicolon, "SEMICOLON", 0xBA, 0x0000003B, 0x0000, 0),
+    (KeyCode::Semicolon, "SEMICOLON", 0xBA, 0x0000003A, 0x0000, 0),
+    (KeyCode::Equals, "EQUALS", 0xBB, 0x000000BB, 0x003D, 13),
+    (KeyCode::Equals, "EQUALS", 0xBB, 0x0000003D, 0x0000, 0),
+    (KeyCode::Comma, "COMMA", 0xBC, 0x000000BC, 0x002C, 51),
+    (KeyCode::Comma, "COMMA", 0xBC, 0x0000003C, 0x0000, 0),
+    (KeyCode::Comma, "COMMA", 0xBC, 0x0000002C, 0x0000, 0),
+    (KeyCode::Minus, "MINUS", 0xBD, 0x000000BD, 0x002D, 12),
+    (KeyCode::Minus, "MINUS", 0xBD, 0x0000005F, 0x0000, 0),
+    (KeyCode::Minus, "MINUS", 0xBD, 0x0000002D, 0x0000, 0),
+    (KeyCode::Period, "PERIOD", 0xBE, 0x000000BE, 0x002E, 52),
+    (KeyCode::Period, "PERIOD", 0xBE, 0x0000003E, 0x0000, 0),
+    (KeyCode::Period, "PERIOD", 0xBE, 0x0000002E, 0x0000, 0),
+    (KeyCode::Slash, "SLASH", 0xBF, 0x000000BF, 0x002F, 53),
+    (KeyCode::Slash, "SLASH", 0xBF, 0x0000002F, 0x0000, 0),
+    (KeyCode::Grave, "GRAVE", 0xC0, 0x000000C0, 0x0060, 41),
+    (KeyCode::LeftBracket, "LEFTBRACKET", 0xDB, 0x000000DB, 0x005B, 26),
+    (KeyCode::LeftBracket, "LEFTBRACKET", 0xDB, 0x0000005B, 0x0000, 0),
+    (KeyCode::Backslash, "BACKSLASH", 0xDC, 0x000000DC, 0x005C, 43),
+    (KeyCode::Backslash, "BACKSLASH", 0xDC, 0x0000005C, 0x0000, 0),
+    (KeyCode::RightBracket, "RIGHTBRACKET", 0xDD, 0x000000DD, 0x005D, 27),
+    (KeyCode::RightBracket, "RIGHTBRACKET", 0xDD, 0x0000005D, 0x0000, 0),
+    (KeyCode::Apostrophe, "APOSTROPHE", 0xDE, 0x000000DE, 0x0027, 40),
+    (KeyCode::Apostrophe, "APOSTROPHE", 0xDE, 0x00000022, 0x0000, 0),
+    (KeyCode::Apostrophe, "APOSTROPHE", 0xDE, 0x00000027, 0x0000, 0),
+    (KeyCode::Oem8, "OEM8", 0xDF, 0x000000DF, 0x0000, 0),
+    (KeyCode::Oem102, "OEM102", 0xE2, 0x00000000, 0x0000, 86),
+    (KeyCode::Play, "PLAY", 0xFA, 0x01020005, 0x0000, 0),
+    (KeyCode::Zoom, "ZOOM", 0xFB, 0x01020006, 0x0000, 0),
+];
+
+impl KeyCode {
+    pub fn from_vk(code: u32) -> KeyCode {
+        if code == 0 {
+            return KeyCode::None;
+        }
+
+        return KEY_CODES.iter().find(|entry| entry.2 == code).map_or(KeyCode::Unknown(code), |entry| entry.0);
+    }
+
+    // Only virtual-key codes can be kept as Unknown. Qt, X11 and evdev codes without an entry give None, so an unknown key
+    // never turns into KeyCode::None and matches every unbound key.
+    pub fn from_qt(code: u32) -> Option<KeyCode> {
+        return KEY_CODES.iter().find(|entry| entry.3 == code && code != 0).map(|entry| entry.0);
+    }
+
+    pub fn from_keysym(keysym: u32) -> Option<KeyCode> {
+        return KEY_CODES.iter().find(|entry| entry.4 == keysym && keysym != 0).map(|entry| entry.0);
+    }
+
+    pub fn from_evdev(code: u16) -> Option<KeyCode> {
+        return KEY_CODES.iter().find(|entry| entry.5 == code && code != 0).map(|entry| entry.0);
+    }
+
+    // Accepts a display name in any case, or a virtual-key code in decimal or 0x prefixed hexadecimal.
+    pub fn from_name(name: &str) -> Option<KeyCode> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Some(KeyCode::None);
+        }
+
+        if let Some(entry) = KEY_CODES.iter().find(|entry| entry.1.eq_ignore_ascii_case(name)) {
+            return Some(entry.0);
+        }
+
+        if name.starts_with("0x") || name.starts_with("0X") {
+            return u32::from_str_radix(&name[2..], 16).ok().map(KeyCode::from_vk);
+        }
+
+        return name.parse::<u32>().ok().map(KeyCode::from_vk);
+    }
+
+    #[allow(dead_code)]
+    pub fn to_vk(self) -> u32 {
+        if let KeyCode::Unknown(code) = self {
+            return code;
+        }
+
+        return KEY_CODES.iter().find(|entry| entry.0 == self).map_or(0, |entry| entry.2);
+    }
+
+    #[allow(dead_code)]
+    pub fn to_qt(self) -> u32 {
+        return KEY_CODES.iter().find(|entry| entry.0 == self && entry.3 != 0).map_or(0, |entry| entry.3);
+    }
+
+    pub fn to_keysym(self) -> u32 {
+        return KEY_CODES.iter().find(|entry| entry.0 == self && entry.4 != 0).map_or(0, |entry| entry.4);
+    }
+
+    #[allow(dead_code)]
+    pub fn to_evdev(self) -> u16 {
+        return KEY_CODES.iter().find(|entry| entry.0 == self && entry.5 != 0).map_or(0, |entry| entry.5);
+    }
+}
+
+// Writes the name from_name reads back. Unknown keys are written as their virtual-key code.
+impl fmt::Display for KeyCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            KeyCode::None => Ok(()),
+            KeyCode::Unknown(code) => write!(f, "{}", code),
+            _ => write!(f, "{}", KEY_CODES.iter().find(|entry| entry.0 == *self).map_or("", |entry| entry.1)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_codes_are_not_unbound_keys() {
+        assert_eq!(KeyCode::from_qt(0x01FFFFFF), None);
+        assert_eq!(KeyCode::from_keysym(0x1008FF12), None);
+        assert_eq!(KeyCode::from_evdev(0x110), None);
+        assert_eq!(KeyCode::from_qt(0), None);
+        assert_eq!(KeyCode::from_keysym(0), None);
+        assert_eq!(KeyCode::from_evdev(0), None);
+        assert_eq!(KeyCode::from_vk(0xE8), KeyCode::Unknown(0xE8));
+    }
+
+    #[test]
+    fn known_codes_convert() {
+        assert_eq!(KeyCode::from_qt(0x41), Some(KeyCode::A));
+        assert_eq!(KeyCode::from_keysym(0x0061), Some(KeyCode::A));
+        assert_eq!(KeyCode::from_evdev(30), Some(KeyCode::A));
+        assert_eq!(KeyCode::from_vk(0x41), KeyCode::A);
+    }
+}
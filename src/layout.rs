@@ -0,0 +1,222 @@
+use crate::dpad::*;
+use crate::ini::*;
+use crate::key::*;
+
+// Where every key goes and how it is drawn. See layouts/default.ini for the format, it is also the layout used when none is configured.
+pub struct Layout {
+    pub width: f32,
+    pub height: f32,
+    pub text_row: f32,
+    pub dpad_center: Option<(f32, f32, i32)>,
+    pub keys: Vec<LayoutKey>,
+}
+
+pub struct LayoutKey {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub sprite: i32,
+    pub gambatte_entry: String,
+    pub count: Option<(f32, f32)>,
+    pub dpad: Option<LayoutDpad>,
+}
+
+pub struct LayoutDpad {
+    pub pressed_x_shift: i32,
+    pub pressed_y_shift: i32,
+    pub overhang: [i32; 6],
+    pub arrow: [i32; 3],
+}
+
+pub const DEFAULT_LAYOUT: &str = include_str!("layouts/default.ini");
+
+// Layouts that can be picked by name instead of a path.
+const BUILT_IN_LAYOUTS: &[(&str, &str)] = &[("default", DEFAULT_LAYOUT), ("gba", include_str!("layouts/gba.ini"))];
+
+const LAYOUT_SECTION: &str = "layout";
+
+// In keys. Far larger than any controller, but small enough that the window always fits in memory.
+const MAX_LAYOUT_SIZE: f32 = 64.0;
+
+// Sprites are counted in the embedded sheets, which every skin has at least as many of. The first row of keys and the first half of
+// the arrows are drawn by the layout, the others are their pressed, contracted and elongated versions picked through the state bits.
+const KEY_SHEET_SPRITES: i32 = 80;
+const KEY_SPRITES: i32 = KEY_PRESSED_BIT;
+const ARROW_SPRITES: i32 = ARROW_PRESSED_BIT;
+
+// In pixels. Shifts and overhangs move parts of a key by a few pixels, the arrow sits inside its key.
+const MAX_OFFSET: i32 = 64;
+
+pub fn parse_list<T: std::str::FromStr>(value: &str) -> Option<Vec<T>> {
+    return value.split(',').map(|item| item.trim().parse::<T>().ok()).collect();
+}
+
+impl Layout {
+    pub fn parse(text: &str) -> Result<Layout, String> {
+        let ini = Ini::parse(text);
+
+        let get = |section: &str, name: &str| ini.get(section, name).ok_or_else(|| format!("[{}] is missing {}", section, name));
+        let invalid = |section: &str, name: &str| format!("[{}] has an invalid {}", section, name);
+
+        let size = parse_list::<f32>(get(LAYOUT_SECTION, "size")?).filter(|size| size.len() == 2 && size.iter().all(|length| *length > 0.0 && *length <= MAX_LAYOUT_SIZE)).ok_or_else(|| invalid(LAYOUT_SECTION, "size"))?;
+        let inside = |x: f32, y: f32| (0.0..size[0]).contains(&x) && (0.0..size[1]).contains(&y);
+        let mut layout = Layout {
+            width: size[0],
+            height: size[1],
+            text_row: get(LAYOUT_SECTION, "text")?.parse().ok().filter(|row| (0.0..size[1]).contains(row)).ok_or_else(|| invalid(LAYOUT_SECTION, "text"))?,
+            dpad_center: None,
+            keys: Vec::new(),
+        };
+
+        if let Some(value) = ini.get(LAYOUT_SECTION, "dpad_center") {
+            match parse_list::<f32>(value).as_deref() {
+                Some([x, y, sprite]) if inside(*x, *y) && sprite.fract() == 0.0 && (0.0..KEY_SHEET_SPRITES as f32).contains(sprite) => layout.dpad_center = Some((*x, *y, *sprite as i32)),
+                _ => return Err(invalid(LAYOUT_SECTION, "dpad_center")),
+            }
+        }
+
+        for (name, _) in ini.sections.iter().filter(|section| !section.0.is_empty() && section.0 != LAYOUT_SECTION) {
+            let position = parse_list::<f32>(get(name, "position")?).filter(|position| position.len() == 2 && inside(position[0], position[1])).ok_or_else(|| invalid(name, "position"))?;
+
+            let mut key = LayoutKey {
+                name: name.to_uppercase(),
+                x: position[0],
+                y: position[1],
+                sprite: get(name, "sprite")?.parse().ok().filter(|sprite| (0..KEY_SPRITES).contains(sprite)).ok_or_else(|| invalid(name, "sprite"))?,
+                gambatte_entry: ini.get(name, "gambatte").unwrap_or("").to_string(),
+                count: None,
+                dpad: None,
+            };
+
+            if let Some(value) = ini.get(name, "count") {
+                match parse_list::<f32>(value).as_deref() {
+                    Some([x, y]) if inside(*x, *y) => key.count = Some((*x, *y)),
+                    _ => return Err(invalid(name, "count")),
+                }
+            }
+
+            if ini.get(name, "dpad") == Some("true") {
+                // Only destinations and shifts can be negative.
+                let offset = |value: &i32| value.abs() <= MAX_OFFSET;
+                let length = |value: &i32| (0..=MAX_OFFSET).contains(value);
+                let shift = parse_list::<i32>(get(name, "shift")?).filter(|shift| shift.len() == 2 && shift.iter().all(offset)).ok_or_else(|| invalid(name, "shift"))?;
+                let overhang = parse_list::<i32>(get(name, "overhang")?).filter(|overhang| overhang.len() == 6 && [0, 1, 4, 5].iter().all(|i| length(&overhang[*i])) && overhang[2..4].iter().all(offset)).ok_or_else(|| invalid(name, "overhang"))?;
+                let arrow = parse_list::<i32>(get(name, "arrow")?).filter(|arrow| arrow.len() == 3 && (0..ARROW_SPRITES).contains(&arrow[0]) && arrow[1..].iter().all(length)).ok_or_else(|| invalid(name, "arrow"))?;
+
+                key.dpad = Some(LayoutDpad {
+                    pressed_x_shift: shift[0],
+                    pressed_y_shift: shift[1],
+                    overhang: [overhang[0], overhang[1], overhang[2], overhang[3], overhang[4], overhang[5]],
+                    arrow: [arrow[0], arrow[1], arrow[2]],
+                });
+            }
+
+            layout.keys.push(key);
+        }
+
+        if layout.keys.is_empty() {
+            return Err(String::from("no keys"));
+        }
+
+        return Ok(layout);
+    }
+
+    pub fn default_layout() -> Layout {
+        return Layout::parse(DEFAULT_LAYOUT).unwrap();
+    }
+
+    // Takes the name of a built-in layout or a path. Falls back to the default layout when the file cannot be used.
+    pub fn load(path: Option<&str>) -> Layout {
+        let path = match path {
+            Some(path) => path,
+            None => return Layout::default_layout(),
+        };
+
+        if let Some((_, text)) = BUILT_IN_LAYOUTS.iter().find(|layout| layout.0.eq_ignore_ascii_case(path)) {
+            return Layout::parse(text).unwrap();
+        }
+
+        match std::fs::read_to_string(path).map_err(|error| error.to_string()).and_then(|text| Layout::parse(&text)) {
+            Ok(layout) => return layout,
+            Err(error) => {
+                eprintln!("{}: {}, using the default layout", path, error);
+                return Layout::default_layout();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "[a]\nposition = 1, 1\nsprite = 0\n";
+
+    fn parse(layout: &str) -> Result<Layout, String> {
+        return Layout::parse(&format!("[layout]\n{}\n{}", layout, KEY));
+    }
+
+    #[test]
+    fn built_in_layouts_parse() {
+        for (name, text) in BUILT_IN_LAYOUTS.iter() {
+            assert!(Layout::parse(text).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn sizes_must_be_positive_and_bounded() {
+        assert!(parse("size = 10, 8\ntext = 7").is_ok());
+        assert!(parse("size = -1, 8\ntext = 7").is_err());
+        assert!(parse("size = 0, 8\ntext = 7").is_err());
+        assert!(parse("size = 10, 1e9\ntext = 7").is_err());
+        assert!(parse("size = NaN, 8\ntext = 7").is_err());
+    }
+
+    #[test]
+    fn rows_and_positions_must_be_inside_the_window() {
+        assert!(parse("size = 10, 8\ntext = 8").is_err());
+        assert!(parse("size = 10, 8\ntext = -1").is_err());
+        assert!(parse("size = 10, 8\ntext = 7\ndpad_center = 10, 0, 1").is_err());
+        assert!(Layout::parse(&format!("[layout]\nsize = 10, 8\ntext = 7\n[b]\nposition = -0.5, 1\nsprite = 0\n{}", KEY)).is_err());
+        assert!(Layout::parse(&format!("[layout]\nsize = 10, 8\ntext = 7\n[b]\nposition = 1, 8\nsprite = 0\n{}", KEY)).is_err());
+        assert!(Layout::parse(&format!("[layout]\nsize = 10, 8\ntext = 7\n[b]\nposition = 1, 2\nsprite = 0\ncount = 1, 9\n{}", KEY)).is_err());
+    }
+
+    #[test]
+    fn counts_are_optional() {
+        let layout = Layout::parse(&format!("[layout]\nsize = 10, 8\ntext = 7\n[b]\nposition = 1, 2\nsprite = 0\ncount = 1, 3.05\n{}", KEY)).unwrap();
+        assert_eq!(layout.keys[0].count, Some((1.0, 3.05)));
+        assert_eq!(layout.keys[1].count, None);
+    }
+
+    fn key(lines: &str) -> Result<Layout, String> {
+        return Layout::parse(&format!("[layout]\nsize = 10, 8\ntext = 7\n[b]\nposition = 1, 2\n{}\n", lines));
+    }
+
+    const DPAD: &str = "dpad = true\nshift = 0, -2\noverhang = 2, 32, 2, 34, 30, 2\narrow = 0, 10, 8";
+
+    #[test]
+    fn sprites_must_be_on_the_sheet() {
+        assert!(key("sprite = 15").is_ok());
+        assert!(key("sprite = 16").is_err());
+        assert!(key("sprite = -1").is_err());
+        assert!(key("sprite = 2147483000").is_err());
+        assert!(parse("size = 10, 8\ntext = 7\ndpad_center = 2, 3, 79").is_ok());
+        assert!(parse("size = 10, 8\ntext = 7\ndpad_center = 2, 3, 80").is_err());
+        assert!(parse("size = 10, 8\ntext = 7\ndpad_center = 2, 3, -1").is_err());
+        assert!(parse("size = 10, 8\ntext = 7\ndpad_center = 2, 3, 1.5").is_err());
+    }
+
+    #[test]
+    fn dpad_offsets_must_be_small() {
+        assert!(key(&format!("sprite = 5\n{}", DPAD)).is_ok());
+        assert!(key(&format!("sprite = 5\n{}", DPAD.replace("shift = 0, -2", "shift = 0, -65"))).is_err());
+        assert!(key(&format!("sprite = 5\n{}", DPAD.replace("shift = 0, -2", "shift = 2147483647, 0"))).is_err());
+        assert!(key(&format!("sprite = 5\n{}", DPAD.replace("overhang = 2, 32, 2, 34", "overhang = -1, 32, 2, 34"))).is_err());
+        assert!(key(&format!("sprite = 5\n{}", DPAD.replace("overhang = 2, 32, 2, 34", "overhang = 2, 32, -2, -65"))).is_err());
+        assert!(key(&format!("sprite = 5\n{}", DPAD.replace("30, 2", "30, -2"))).is_err());
+        assert!(key(&format!("sprite = 5\n{}", DPAD.replace("arrow = 0, 10, 8", "arrow = 4, 10, 8"))).is_err());
+        assert!(key(&format!("sprite = 5\n{}", DPAD.replace("arrow = 0, 10, 8", "arrow = 0, -1, 8"))).is_err());
+        assert!(key(&format!("sprite = 5\n{}", DPAD.replace("arrow = 0, 10, 8", "arrow = 0, 10, 65"))).is_err());
+    }
+}
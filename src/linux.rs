@@ -0,0 +1,50 @@
+#![allow(dead_code)]
+
+use std::os::raw::*;
+
+#[repr(C)]
+pub struct PollFd {
+    pub fd: c_int,
+    pub events: c_short,
+    pub revents: c_short,
+}
+
+#[repr(C)]
+pub struct InputEvent {
+    pub time: [c_long; 2],
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+extern "C" {
+    pub fn poll(fds: *mut PollFd, count: c_ulong, timeout: c_int) -> c_int;
+    pub fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+
+    pub fn shmget(key: c_int, size: usize, flags: c_int) -> c_int;
+    pub fn shmat(id: c_int, address: *const c_void, flags: c_int) -> *mut c_void;
+    pub fn shmdt(address: *const c_void) -> c_int;
+    pub fn shmctl(id: c_int, command: c_int, buffer: *mut c_void) -> c_int;
+}
+
+pub const POLLIN: c_short = 0x001;
+
+pub const O_NONBLOCK: i32 = 0o4000;
+
+pub const IPC_PRIVATE: c_int = 0;
+pub const IPC_CREAT: c_int = 0o1000;
+pub const IPC_RMID: c_int = 0;
+
+pub const EV_KEY: u16 = 0x01;
+pub const KEY_MAX: usize = 0x2ff;
+pub const KEY_A: usize = 30;
+pub const KEY_Z: usize = 44;
+
+pub const KEY_EVENT_RELEASE: i32 = 0;
+pub const KEY_EVENT_PRESS: i32 = 1;
+pub const KEY_EVENT_REPEAT: i32 = 2;
+
+// _IOC(_IOC_READ, 'E', 0x20 + event_type, length)
+pub const fn eviocgbit(event_type: u16, length: usize) -> c_ulong {
+    return (2 << 30) | ((length as c_ulong) << 16) | ((b'E' as c_ulong) << 8) | (0x20 + event_type as c_ulong);
+}
@@ -1,4 +1,5 @@
 #![windows_subsystem = "windows"]
+#![allow(clippy::needless_return, clippy::redundant_field_names, clippy::too_many_arguments)]
 
 mod application;
 mod bmp;
@@ -6,10 +7,59 @@ mod configuration;
 mod dpad;
 mod gambatte;
 mod gfx;
+mod history;
+mod ini;
+mod input_log;
 mod key;
+mod keymap;
+mod layout;
+#[cfg(target_os = "linux")]
+mod linux;
+mod notification;
+mod palette;
 mod platform;
+mod registry;
+mod skin;
+mod stats;
+#[cfg(windows)]
 mod winapi;
+#[cfg(target_os = "linux")]
+mod xlib;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// Usage: gbc-input-display --headless <script> <output directory>
+// Renders every frame produced by the script as frame_NNNN.bmp into the output directory.
+fn run_headless(script_path: &str, output_path: &str) -> Result<(), String> {
+    let text = std::fs::read_to_string(script_path).map_err(|e| format!("{}: {}", script_path, e))?;
+    let script = platform::Script::parse(&text).map_err(|e| format!("{}: {}", script_path, e))?;
+    std::fs::create_dir_all(output_path).map_err(|e| format!("{}: {}", output_path, e))?;
+
+    for (i, frame) in application::render_headless(script).iter().enumerate() {
+        let path = Path::new(output_path).join(format!("frame_{:04}.bmp", i));
+        let mut file = BufWriter::new(File::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?);
+        bmp::bmp_save(&mut file, frame.width, frame.height, &frame.memory, frame.transparent_background).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+
+    return Ok(());
+}
 
 fn main() {
-    application::start();
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() == 4 && args[1] == "--headless" {
+        if let Err(error) = run_headless(&args[2], &args[3]) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Usage: gbc-input-display --device <path>
+    // Reads keys from a single input device instead of every keyboard. Only used on Linux.
+    let input_device = if args.len() == 3 && args[1] == "--device" { Some(args[2].as_str()) } else { None };
+
+    application::start(input_device);
 }
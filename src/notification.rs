@@ -0,0 +1,200 @@
+use std::time::*;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Priority {
+    Normal,
+    High,
+}
+
+// Whoever posted a notification. A source only ever has one notification queued, posting again replaces it.
+#[derive(PartialEq, Clone, Copy)]
+pub enum NotificationSource {
+    GambatteSync,
+    Configuration,
+    Palettes,
+}
+
+pub struct Notification {
+    pub text: String,
+    pub priority: Priority,
+    pub duration: Option<Duration>,
+    pub source: NotificationSource,
+}
+
+// Messages shown in the text line, one at a time. The first entry is the one on screen, the rest wait ordered by priority.
+// Nothing here keeps time: whoever displays the notifications arms a timer for the current duration and calls expire when it runs out.
+pub struct Notifications {
+    pub queue: Vec<Notification>,
+}
+
+impl Notification {
+    pub fn new(text: &str, priority: Priority, duration: Option<Duration>, source: NotificationSource) -> Notification {
+        return Notification { text: text.to_string(), priority: priority, duration: duration, source: source };
+    }
+}
+
+impl Notifications {
+    pub fn new() -> Notifications {
+        return Notifications { queue: Vec::new() };
+    }
+
+    pub fn current(&self) -> Option<&Notification> {
+        return self.queue.first();
+    }
+
+    pub fn text(&self) -> &str {
+        return self.current().map_or("", |notification| notification.text.as_str());
+    }
+
+    // Queues behind everything of the same or higher priority. A higher priority notification takes over the screen right away,
+    // the one it replaces is shown again, with its full duration, once it is gone. Returns true when the current notification changed.
+    pub fn post(&mut self, notification: Notification) -> bool {
+        let mut changed = self.remove(notification.source);
+
+        let index = self.queue.iter().position(|queued| queued.priority < notification.priority).unwrap_or(self.queue.len());
+        self.queue.insert(index, notification);
+
+        changed |= index == 0;
+        return changed;
+    }
+
+    // Returns true when the current notification changed.
+    pub fn dismiss(&mut self, source: NotificationSource) -> bool {
+        return self.remove(source);
+    }
+
+    // Called when the duration of the current notification is over. Returns true when the current notification changed.
+    pub fn expire(&mut self) -> bool {
+        if self.current().is_some_and(|notification| notification.duration.is_some()) {
+            self.queue.remove(0);
+            return true;
+        }
+
+        return false;
+    }
+
+    fn remove(&mut self, source: NotificationSource) -> bool {
+        match self.queue.iter().position(|queued| queued.source == source) {
+            Some(index) => {
+                self.queue.remove(index);
+                return index == 0;
+            }
+            None => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Shows the notifications the way the application does: a deadline is set whenever the current notification changes,
+    // and expire is called once the clock reaches it.
+    struct Screen {
+        notifications: Notifications,
+        deadline: Option<Duration>,
+        now: Duration,
+    }
+
+    impl Screen {
+        fn new() -> Screen {
+            return Screen { notifications: Notifications::new(), deadline: None, now: Duration::ZERO };
+        }
+
+        fn changed(&mut self) {
+            self.deadline = self.notifications.current().and_then(|notification| notification.duration).map(|duration| self.now + duration);
+        }
+
+        fn post(&mut self, text: &str, priority: Priority, seconds: Option<u64>, source: NotificationSource) {
+            if self.notifications.post(Notification::new(text, priority, seconds.map(Duration::from_secs), source)) {
+                self.changed();
+            }
+        }
+
+        fn dismiss(&mut self, source: NotificationSource) {
+            if self.notifications.dismiss(source) {
+                self.changed();
+            }
+        }
+
+        // Moves the clock to the given second, expiring everything that runs out on the way.
+        fn at(&mut self, seconds: u64) -> &str {
+            let time = Duration::from_secs(seconds);
+            while let Some(deadline) = self.deadline.filter(|deadline| *deadline <= time) {
+                self.now = deadline;
+                self.deadline = None;
+                if self.notifications.expire() {
+                    self.changed();
+                }
+            }
+
+            self.now = time;
+            return self.notifications.text();
+        }
+    }
+
+    #[test]
+    fn timed_notifications_expire_in_order() {
+        let mut screen = Screen::new();
+        screen.post("FIRST", Priority::Normal, Some(3), NotificationSource::GambatteSync);
+        screen.post("SECOND", Priority::Normal, Some(2), NotificationSource::Configuration);
+
+        assert_eq!(screen.at(0), "FIRST");
+        assert_eq!(screen.at(2), "FIRST");
+        assert_eq!(screen.at(3), "SECOND");
+        assert_eq!(screen.at(4), "SECOND");
+        assert_eq!(screen.at(5), "");
+    }
+
+    #[test]
+    fn higher_priority_takes_over_and_the_other_comes_back_in_full() {
+        let mut screen = Screen::new();
+        screen.post("SYNC", Priority::Normal, Some(3), NotificationSource::GambatteSync);
+        assert_eq!(screen.at(1), "SYNC");
+
+        screen.post("NAME", Priority::High, None, NotificationSource::Configuration);
+        assert_eq!(screen.at(10), "NAME");
+
+        screen.dismiss(NotificationSource::Configuration);
+        assert_eq!(screen.at(12), "SYNC");
+        assert_eq!(screen.at(13), "");
+    }
+
+    #[test]
+    fn lower_priority_waits() {
+        let mut screen = Screen::new();
+        screen.post("NAME", Priority::High, Some(2), NotificationSource::Configuration);
+        screen.post("SYNC", Priority::Normal, Some(2), NotificationSource::GambatteSync);
+        assert_eq!(screen.at(1), "NAME");
+        assert_eq!(screen.at(2), "SYNC");
+        assert_eq!(screen.at(4), "");
+    }
+
+    #[test]
+    fn posting_again_replaces_the_message_of_that_source() {
+        let mut screen = Screen::new();
+        screen.post("SUCCESS", Priority::Normal, Some(3), NotificationSource::GambatteSync);
+        screen.post("PALETTE", Priority::Normal, Some(3), NotificationSource::Configuration);
+        assert_eq!(screen.at(2), "SUCCESS");
+
+        // The replacement starts over with its own duration.
+        screen.post("FAILURE", Priority::Normal, Some(3), NotificationSource::GambatteSync);
+        assert_eq!(screen.notifications.queue.len(), 2);
+        assert_eq!(screen.at(2), "PALETTE");
+        assert_eq!(screen.at(5), "FAILURE");
+        assert_eq!(screen.at(8), "");
+    }
+
+    #[test]
+    fn untimed_notifications_stay_until_dismissed() {
+        let mut screen = Screen::new();
+        screen.post("NAME", Priority::High, None, NotificationSource::Configuration);
+        assert!(!screen.notifications.expire());
+        assert_eq!(screen.at(100), "NAME");
+
+        screen.dismiss(NotificationSource::GambatteSync);
+        assert_eq!(screen.at(100), "NAME");
+        screen.dismiss(NotificationSource::Configuration);
+        assert_eq!(screen.at(100), "");
+    }
+}
@@ -0,0 +1,267 @@
+use crate::application::*;
+use crate::configuration::*;
+use crate::gfx::*;
+use crate::ini::*;
+use crate::keymap::*;
+use crate::notification::*;
+
+use std::io::*;
+use std::path::*;
+use std::time::*;
+
+pub const DEFAULT_PALETTE: &str = "Green";
+
+// User palettes live in this section of the configuration file, and of the file palettes are imported from and exported to.
+// Each entry is named after its palette and lists the four colours, e.g. "Pocket = #000000, #8bac0f, #306230, #9bbc0f".
+pub const PALETTES_SECTION: &str = "palettes";
+const PALETTE_FILE: &str = "palettes.ini";
+
+const MAX_NAME_LENGTH: usize = 12;
+const PALETTE_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+pub const BUILT_IN_PALETTES: &[(&str, [Color; 4])] = &[
+    ("Brown", [[0, 0, 0], [228, 150, 133], [228, 150, 133], [248, 248, 248]]),
+    ("Pastel Mix", [[0, 0, 0], [228, 144, 163], [228, 144, 163], [242, 226, 187]]),
+    ("Blue", [[0, 0, 0], [225, 128, 150], [113, 182, 208], [248, 248, 248]]),
+    ("Green", [[0, 0, 0], [96, 186, 46], [96, 186, 46], [248, 248, 248]]),
+    ("Red", [[0, 0, 0], [131, 198, 86], [225, 128, 150], [248, 248, 248]]),
+    ("Orange", [[0, 0, 0], [232, 186, 77], [232, 186, 77], [248, 248, 248]]),
+    ("Dark Blue", [[0, 0, 0], [225, 128, 150], [141, 156, 191], [248, 248, 248]]),
+    ("Dark Green", [[0, 0, 0], [225, 128, 150], [131, 198, 86], [248, 248, 248]]),
+    ("Dark Brown", [[78, 38, 28], [228, 150, 133], [189, 146, 144], [241, 216, 206]]),
+    ("Yellow", [[0, 0, 0], [113, 182, 208], [232, 186, 77], [248, 248, 248]]),
+    ("Monochrome", [[0, 0, 0], [160, 160, 160], [160, 160, 160], [248, 248, 248]]),
+    ("Inverted", [[248, 248, 248], [24, 128, 104], [24, 128, 104], [0, 0, 0]]),
+];
+
+pub fn built_in_palettes() -> Vec<(String, Palette)> {
+    return BUILT_IN_PALETTES.iter().map(|(name, colors)| (name.to_string(), colors.to_vec())).collect();
+}
+
+pub fn is_built_in(index: usize) -> bool {
+    return index < BUILT_IN_PALETTES.len();
+}
+
+// Four colours written as #rrggbb, separated by commas.
+pub fn parse_palette(value: &str) -> Option<Palette> {
+    let colors: Vec<Color> = value
+        .split(',')
+        .map(|color| {
+            let hex = color.trim().trim_start_matches('#');
+            // from_str_radix also takes a sign, so the digits are checked first.
+            if hex.len() != 6 || !hex.chars().all(|digit| digit.is_ascii_hexdigit()) {
+                return None;
+            }
+            let rgb = u32::from_str_radix(hex, 16).ok()?;
+            Some([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8])
+        })
+        .collect::<Option<_>>()?;
+
+    return if colors.len() == 4 { Some(colors) } else { None };
+}
+
+pub fn format_palette(palette: &Palette) -> String {
+    return palette.iter().map(|color| format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])).collect::<Vec<String>>().join(", ");
+}
+
+// Entries that do not hold four colours are skipped.
+pub fn read_palettes(ini: &Ini) -> Vec<(String, Palette)> {
+    return ini.section(PALETTES_SECTION).unwrap_or(&[]).iter().filter_map(|(name, value)| Some((name.clone(), parse_palette(value)?))).collect();
+}
+
+// Replaces the whole section, so deleted palettes do not linger in the file.
+pub fn write_palettes(ini: &mut Ini, palettes: &[(String, Palette)]) {
+    ini.remove_section(PALETTES_SECTION);
+    for (name, palette) in palettes.iter() {
+        ini.set(PALETTES_SECTION, name, &format_palette(palette));
+    }
+}
+
+// Next to the configuration file.
+pub fn palette_file_path() -> Option<PathBuf> {
+    return configuration_path().and_then(|path| path.parent().map(|directory| directory.join(PALETTE_FILE)));
+}
+
+pub fn find_palette(app: &Application, name: &str) -> Option<usize> {
+    return app.palettes.iter().position(|palette| palette.0.eq_ignore_ascii_case(name));
+}
+
+// Falls back to the default palette when there is none with that name.
+pub fn select_palette(app: &mut Application, name: &str) {
+    let index = find_palette(app, name).or_else(|| find_palette(app, DEFAULT_PALETTE)).unwrap_or(0);
+    change_palette(app, index);
+}
+
+// User palettes named like a built-in one are ignored, one named like another user palette replaces its colours.
+pub fn add_user_palettes(app: &mut Application, palettes: &[(String, Palette)]) -> usize {
+    let mut added = 0;
+
+    for (name, palette) in palettes.iter() {
+        match find_palette(app, name) {
+            Some(index) if is_built_in(index) => continue,
+            Some(index) => app.palettes[index].1 = palette.clone(),
+            None => app.palettes.push((name.clone(), palette.clone())),
+        }
+        added += 1;
+    }
+
+    return added;
+}
+
+pub fn user_palettes(app: &Application) -> Vec<(String, Palette)> {
+    return app.palettes[BUILT_IN_PALETTES.len()..].to_vec();
+}
+
+// Copies the current palette and asks for a name for it.
+pub fn add_palette(app: &mut Application) {
+    let name = (1..).map(|number| format!("Custom {}", number)).find(|name| find_palette(app, name).is_none()).unwrap();
+    app.palettes.push((name, app.palettes[app.palette_index].1.clone()));
+    change_palette(app, app.palettes.len() - 1);
+    start_renaming_palette(app);
+}
+
+pub fn delete_palette(app: &mut Application) {
+    if is_built_in(app.palette_index) {
+        return;
+    }
+
+    app.palettes.remove(app.palette_index);
+    change_palette(app, app.palette_index - 1);
+}
+
+// The new name is typed in, Enter keeps it and Escape leaves the old one.
+pub fn start_renaming_palette(app: &mut Application) {
+    if is_built_in(app.palette_index) || app.key_to_configure != -1 {
+        return;
+    }
+
+    app.palette_name = Some(String::new());
+    show_palette_name(app);
+}
+
+pub fn edit_palette_name(app: &mut Application, key_code: KeyCode) {
+    let mut name = match app.palette_name.take() {
+        Some(name) => name,
+        None => return,
+    };
+
+    match key_code {
+        KeyCode::Enter => {
+            let name = name.trim().to_string();
+            let taken = find_palette(app, &name).is_some_and(|index| index != app.palette_index);
+            // The palette may have changed since the rename started, built-in palettes keep their names.
+            if !name.is_empty() && !taken && !is_built_in(app.palette_index) {
+                app.palettes[app.palette_index].0 = name;
+                dismiss_notification(app, NotificationSource::Palettes);
+                update_menu(app);
+                save_configuration(app).ok();
+                return;
+            }
+        }
+        KeyCode::Escape => {
+            dismiss_notification(app, NotificationSource::Palettes);
+            return;
+        }
+        KeyCode::Backspace => {
+            name.pop();
+        }
+        KeyCode::Space if name.len() < MAX_NAME_LENGTH => name.push(' '),
+        code if name.len() < MAX_NAME_LENGTH => {
+            let text = code.to_string();
+            if text.len() == 1 && text.chars().all(|character| character.is_ascii_alphanumeric()) {
+                name.push_str(&text);
+            }
+        }
+        _ => {}
+    }
+
+    app.palette_name = Some(name);
+    show_palette_name(app);
+}
+
+fn show_palette_name(app: &mut Application) {
+    let text = format!("NAME {}", app.palette_name.as_deref().unwrap_or(""));
+    notify(app, Notification::new(&text, Priority::High, None, NotificationSource::Palettes));
+}
+
+pub fn import_palettes(app: &mut Application) -> Result<usize> {
+    let path = palette_file_path().ok_or_else(|| Error::new(ErrorKind::NotFound, "no configuration directory"))?;
+    let palettes = read_palettes(&Ini::parse(&std::fs::read_to_string(path)?));
+
+    let imported = add_user_palettes(app, &palettes);
+    update_menu(app);
+    return Ok(imported);
+}
+
+pub fn export_palettes(app: &Application) -> Result<()> {
+    let path = palette_file_path().ok_or_else(|| Error::new(ErrorKind::NotFound, "no configuration directory"))?;
+    let mut ini = Ini::default();
+    write_palettes(&mut ini, &user_palettes(app));
+
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    return std::fs::write(path, ini.to_string());
+}
+
+pub fn notify_palettes(app: &mut Application, text: &str) {
+    notify(app, Notification::new(text, Priority::Normal, Some(PALETTE_MESSAGE_DURATION), NotificationSource::Palettes));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::*;
+    use crate::layout::*;
+    use crate::platform::*;
+    use crate::skin::*;
+
+    fn app() -> Application {
+        return Application::new(Box::new(HeadlessPlatform::new(Vec::new())), &Layout::default_layout(), Skin::load(None), InputHistory::new(false, DEFAULT_HISTORY_LENGTH, DEFAULT_HISTORY_SPEED));
+    }
+
+    const POCKET: &str = "#000000, #8bac0f, #306230, #9bbc0f";
+
+    #[test]
+    fn palettes_round_trip() {
+        let palette = parse_palette(POCKET).unwrap();
+        assert_eq!(palette, vec![[0x00, 0x00, 0x00], [0x8b, 0xac, 0x0f], [0x30, 0x62, 0x30], [0x9b, 0xbc, 0x0f]]);
+        assert_eq!(format_palette(&palette), POCKET);
+        assert_eq!(parse_palette("000000,#8BAC0F ,  #306230,9bbc0f"), Some(palette.clone()));
+
+        for (name, colors) in BUILT_IN_PALETTES.iter() {
+            assert_eq!(parse_palette(&format_palette(&colors.to_vec())), Some(colors.to_vec()), "{}", name);
+        }
+
+        let palettes = vec![(String::from("Pocket"), palette), (String::from("Night"), BUILT_IN_PALETTES[11].1.to_vec())];
+        let mut ini = Ini::default();
+        ini.set(PALETTES_SECTION, "Stale", POCKET);
+        write_palettes(&mut ini, &palettes);
+        assert_eq!(read_palettes(&Ini::parse(&ini.to_string())), palettes);
+    }
+
+    #[test]
+    fn malformed_palettes_are_rejected() {
+        for value in ["", "#000000, #8bac0f, #306230", "#000000, #8bac0f, #306230, #9bbc0f, #ffffff", "#000000, #8bac0f, #306230, #9bbc0", "#000000, #8bac0f, #306230, #9bbc0ff", "#000000, #8bac0f, #306230, #9bbcgf", "#000000, #8bac0f, #306230, #+bbc0f", "#000000, #8bac0f,, #306230, #9bbc0f", "#000000; #8bac0f; #306230; #9bbc0f"].iter() {
+            assert_eq!(parse_palette(value), None, "{}", value);
+        }
+
+        let ini = Ini::parse(&format!("[palettes]\nShort = #000000, #8bac0f\nPocket = {}\nBad = #zzzzzz, #8bac0f, #306230, #9bbc0f\n", POCKET));
+        assert_eq!(read_palettes(&ini), vec![(String::from("Pocket"), parse_palette(POCKET).unwrap())]);
+    }
+
+    #[test]
+    fn duplicate_names_are_merged() {
+        let mut app = app();
+        let pocket = parse_palette(POCKET).unwrap();
+        let night = BUILT_IN_PALETTES[11].1.to_vec();
+
+        let added = add_user_palettes(&mut app, &[(String::from("green"), pocket.clone()), (String::from("Pocket"), pocket.clone()), (String::from("POCKET"), night.clone())]);
+        assert_eq!(added, 2);
+        assert_eq!(app.palettes[find_palette(&app, DEFAULT_PALETTE).unwrap()].1, BUILT_IN_PALETTES[3].1.to_vec());
+        assert_eq!(user_palettes(&app), vec![(String::from("Pocket"), night.clone())]);
+
+        assert_eq!(add_user_palettes(&mut app, &[(String::from("pocket"), pocket.clone()), (String::from("Night"), night.clone())]), 2);
+        assert_eq!(user_palettes(&app), vec![(String::from("Pocket"), pocket), (String::from("Night"), night)]);
+    }
+}
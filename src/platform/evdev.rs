@@ -0,0 +1,107 @@
+use crate::linux::*;
+use crate::platform::*;
+
+use std::fs::*;
+use std::io::*;
+use std::mem::*;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::*;
+
+// Keyboards read straight from /dev/input, which keeps working when the window does not have focus.
+// Reading the devices requires the user to be in the group owning them, usually "input".
+pub struct EvdevKeyboards {
+    pub devices: Vec<File>,
+}
+
+fn open_device(path: &Path) -> Result<File> {
+    return OpenOptions::new().read(true).custom_flags(O_NONBLOCK).open(path);
+}
+
+// Anything that reports the letter keys is treated as a keyboard. This skips mice, power buttons and the like.
+fn is_keyboard(device: &File) -> bool {
+    let mut bits = [0u8; KEY_MAX / 8 + 1];
+    if unsafe { ioctl(device.as_raw_fd(), eviocgbit(EV_KEY, bits.len()), bits.as_mut_ptr()) } < 0 {
+        return false;
+    }
+
+    return (KEY_A..=KEY_Z).all(|key| bits[key / 8] & (1 << (key % 8)) > 0);
+}
+
+impl EvdevKeyboards {
+    // Opens the given device, or every readable keyboard under /dev/input when no device is given.
+    pub fn open(device_path: Option<&str>) -> Result<EvdevKeyboards> {
+        if let Some(path) = device_path {
+            return Ok(EvdevKeyboards { devices: vec![open_device(Path::new(path))?] });
+        }
+
+        let mut devices = Vec::new();
+        for entry in read_dir("/dev/input")? {
+            let path = entry?.path();
+            if !path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("event")) {
+                continue;
+            }
+
+            if let Ok(device) = open_device(&path) {
+                if is_keyboard(&device) {
+                    devices.push(device);
+                }
+            }
+        }
+
+        if devices.is_empty() {
+            return Err(Error::new(ErrorKind::NotFound, "no readable keyboard found in /dev/input"));
+        }
+
+        return Ok(EvdevKeyboards { devices: devices });
+    }
+
+    pub fn poll_fds(&self) -> Vec<PollFd> {
+        return self.devices.iter().map(|device| PollFd { fd: device.as_raw_fd(), events: POLLIN, revents: 0 }).collect();
+    }
+
+    // Drains every pending event. Auto-repeat events are dropped, the hook only cares about state changes.
+    pub fn read_events(&mut self) -> Vec<(KeyCode, KeyState)> {
+        let mut events = Vec::new();
+        let mut buffer = [0u8; size_of::<InputEvent>()];
+
+        let mut disconnected = Vec::new();
+
+        for (i, device) in self.devices.iter_mut().enumerate() {
+            loop {
+                match device.read(&mut buffer) {
+                    Ok(size) if size == buffer.len() => {}
+                    Ok(_) => break,
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::Interrupted => break,
+                    Err(_) => {
+                        disconnected.push(i);
+                        break;
+                    }
+                }
+
+                let event = unsafe { std::ptr::read_unaligned(buffer.as_ptr() as *const InputEvent) };
+                if event.kind != EV_KEY {
+                    continue;
+                }
+
+                let key_state = match event.value {
+                    KEY_EVENT_PRESS => KeyState::Pressed,
+                    KEY_EVENT_RELEASE => KeyState::Released,
+                    _ => continue,
+                };
+
+                // Buttons and media keys without a KeyCode would look like an unbound key.
+                if let Some(code) = KeyCode::from_evdev(event.code) {
+                    events.push((code, key_state));
+                }
+            }
+        }
+
+        // Unplugged devices keep reporting errors, so they are dropped instead of being polled forever.
+        for i in disconnected.into_iter().rev() {
+            self.devices.remove(i);
+        }
+
+        return events;
+    }
+}
@@ -0,0 +1,150 @@
+use crate::platform::*;
+
+use std::collections::*;
+
+// Replays a scripted list of key events without ever opening a window. Every presented frame is kept in memory until taken.
+// Time only advances through wait steps, so timers fire at the same point of the script on every run.
+pub struct HeadlessPlatform {
+    pub steps: VecDeque<Step>,
+    pub frames: Vec<BackBuffer>,
+    pub keys_down: HashSet<KeyCode>,
+    pub time: Duration,
+    pub timer: Option<Duration>,
+    pub scale: i32,
+}
+
+pub enum Step {
+    Key(KeyCode, KeyState),
+    Wait(Duration),
+}
+
+// A script is a list of commands, one per line. Blank lines and lines starting with '#' are skipped.
+//   bind <KEY> <key> [key]  binds a controller key by name, e.g. "bind A Z"
+//   palette <name or index> selects one of the built-in palettes
+//   layout <path>           uses a layout file instead of the built-in layout
+//   skin <directory>        draws with the sprite sheets of a skin directory instead of the embedded ones
+//   background transparent  leaves the background transparent, frames then carry an alpha channel
+//   scale <factor>          scales the frames up by a whole factor from 1 to 6
+//   history <seconds> <speed> shows the input history panel, scrolling by speed pixels per second
+//   log <length>            writes the last presses with their hold times in the text line
+//   counts                  shows how often each key was pressed below it
+//   press <key>             sends a key press for the given key
+//   release <key>           sends a key release for the given key
+//   wait <milliseconds>     lets time pass, firing any timer that runs out in the meantime
+// Keys are given by name, or as a virtual-key code in decimal or 0x prefixed hexadecimal.
+pub struct Script {
+    pub bindings: Vec<(String, KeyCode, KeyCode)>,
+    pub palette: Option<String>,
+    pub layout: Option<String>,
+    pub skin: Option<String>,
+    pub transparent_background: bool,
+    pub scale: Option<i32>,
+    pub history: Option<(Duration, i32)>,
+    pub input_log: Option<usize>,
+    pub press_counts: bool,
+    pub steps: Vec<Step>,
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Script, String> {
+        let mut script = Script { bindings: Vec::new(), palette: None, layout: None, skin: None, transparent_background: false, scale: None, history: None, input_log: None, press_counts: false, steps: Vec::new() };
+
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = || format!("line {}: invalid command \"{}\"", line_number + 1, line);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["bind", name, primary] => script.bindings.push((name.to_uppercase(), KeyCode::from_name(primary).ok_or_else(error)?, KeyCode::None)),
+                ["bind", name, primary, secondary] => script.bindings.push((name.to_uppercase(), KeyCode::from_name(primary).ok_or_else(error)?, KeyCode::from_name(secondary).ok_or_else(error)?)),
+                ["layout", path] => script.layout = Some(path.to_string()),
+                ["background", "transparent"] => script.transparent_background = true,
+                ["background", "opaque"] => script.transparent_background = false,
+                ["scale", scale] => script.scale = Some(scale.parse::<i32>().map_err(|_| error())?),
+                ["history", length, speed] => {
+                    let length = length.parse::<f32>().ok().filter(|length| *length > 0.0).ok_or_else(error)?;
+                    script.history = Some((Duration::from_secs_f32(length), speed.parse::<i32>().ok().filter(|speed| *speed > 0).ok_or_else(error)?));
+                }
+                ["log", length] => script.input_log = Some(length.parse::<usize>().ok().filter(|length| *length > 0).ok_or_else(error)?),
+                ["counts"] => script.press_counts = true,
+                ["skin", path] => script.skin = Some(path.to_string()),
+                ["palette", name @ ..] if !name.is_empty() => script.palette = Some(name.join(" ")),
+                ["press", code] => script.steps.push(Step::Key(KeyCode::from_name(code).ok_or_else(error)?, KeyState::Pressed)),
+                ["release", code] => script.steps.push(Step::Key(KeyCode::from_name(code).ok_or_else(error)?, KeyState::Released)),
+                ["wait", milliseconds] => script.steps.push(Step::Wait(Duration::from_millis(milliseconds.parse::<u64>().map_err(|_| error())?))),
+                _ => return Err(error()),
+            }
+        }
+
+        return Ok(script);
+    }
+}
+
+impl HeadlessPlatform {
+    pub fn new(steps: Vec<Step>) -> HeadlessPlatform {
+        return HeadlessPlatform { steps: steps.into(), frames: Vec::new(), keys_down: HashSet::new(), time: Duration::from_secs(0), timer: None, scale: 1 };
+    }
+}
+
+impl Platform for HeadlessPlatform {
+    // The script ending counts as closing the window.
+    fn next_event(&mut self) -> Event {
+        loop {
+            match self.steps.pop_front() {
+                Some(Step::Key(code, key_state)) => {
+                    if key_state == KeyState::Pressed {
+                        self.keys_down.insert(code);
+                    } else {
+                        self.keys_down.remove(&code);
+                    }
+
+                    return Event::KeyChanged(code, key_state);
+                }
+                Some(Step::Wait(duration)) => {
+                    let end = self.time + duration;
+                    match self.timer {
+                        Some(deadline) if deadline <= end => {
+                            self.time = deadline;
+                            self.timer = None;
+                            self.steps.push_front(Step::Wait(end - deadline));
+                            return Event::Timer;
+                        }
+                        _ => self.time = end,
+                    }
+                }
+                None => return Event::Quit,
+            }
+        }
+    }
+
+    fn set_menu(&mut self, _items: Vec<MenuItem>) {}
+
+    fn set_timer(&mut self, timeout: Option<Duration>) {
+        self.timer = timeout.map(|timeout| self.time + timeout);
+    }
+
+    fn time(&self) -> Duration {
+        return self.time;
+    }
+
+    fn update_window(&mut self, buffer: &BackBuffer) {
+        let frame = if self.scale == 1 { buffer.clone() } else { buffer.scaled(self.scale) };
+        self.frames.push(frame);
+    }
+
+    fn set_scale(&mut self, scale: i32) -> Result<(), PlatfromError> {
+        self.scale = scale;
+        return Ok(());
+    }
+
+    fn is_key_down(&self, code: KeyCode) -> bool {
+        return self.keys_down.contains(&code);
+    }
+
+    fn take_frames(&mut self) -> Vec<BackBuffer> {
+        return std::mem::take(&mut self.frames);
+    }
+}
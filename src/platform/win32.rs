@@ -0,0 +1,278 @@
+use crate::gfx::*;
+use crate::platform::*;
+use crate::winapi::*;
+
+use std::cell::*;
+use std::collections::*;
+use std::mem::*;
+use std::ptr::*;
+
+pub struct Win32Platform {
+    pub instance: usize,
+    pub running: bool,
+    pub window_width: i32,
+    pub window_height: i32,
+    pub scale: i32,
+    pub window_handle: usize,
+    pub windows_hooks: Vec<usize>,
+    pub menu: usize,
+    pub start: Instant,
+}
+
+const TIMER_ID: usize = 1;
+const WINDOW_STYLE: u32 = WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE;
+
+thread_local! {
+    // Low level hooks cannot carry any user data, so their events are queued per thread. The hook runs on the thread that installed it.
+    static KEY_EVENTS: RefCell<VecDeque<Event>> = RefCell::new(VecDeque::new());
+}
+
+impl Win32Platform {
+    pub fn new(width: i32, height: i32, scale: i32, title: &str) -> Result<Win32Platform, PlatfromError> {
+        unsafe {
+            let instance = GetModuleHandleW(null());
+            let win_width = width * scale;
+            let win_height = height * scale;
+            let win_handle = Win32Platform::create_window(instance, win_width, win_height, title)?;
+
+            return Ok(Win32Platform {
+                instance: instance,
+                running: true,
+                window_width: win_width,
+                window_height: win_height,
+                scale: scale,
+                window_handle: win_handle,
+                windows_hooks: vec![SetWindowsHookExW(WH_KEYBOARD_LL, Win32Platform::keyboard_hook, instance, 0)],
+                menu: 0,
+                start: Instant::now(),
+            });
+        }
+    }
+
+    fn key_state_from_wparam(wparam: usize) -> KeyState {
+        match wparam as u32 {
+            WM_KEYDOWN => KeyState::Pressed,
+            WM_SYSKEYDOWN => KeyState::Pressed,
+            WM_KEYUP => KeyState::Released,
+            WM_SYSKEYUP => KeyState::Released,
+            _ => KeyState::None,
+        }
+    }
+
+    unsafe fn create_window(instance: usize, width: i32, height: i32, title: &str) -> Result<usize, PlatfromError> {
+        let class = to_unicode(&format!("{}{}", title, "Class"));
+        let title = to_unicode(title);
+
+        let mut window_class = WindowClassW {
+            style: CS_OWNDC | CS_VREDRAW | CS_HREDRAW,
+            window_proc: Win32Platform::windows_message_callback,
+            cls_extra: 0,
+            wnd_extra: 0,
+            instance: instance,
+            icon: 0,
+            cursor: 0,
+            brush: 0,
+            menu_name: null(),
+            class_name: class.as_ptr(),
+        };
+
+        if RegisterClassW(&mut window_class) == 0 {
+            return Err(PlatfromError::WindowClassCreation);
+        }
+
+        let mut window_dimension = Rect { left: 0, top: 0, right: width, bottom: height };
+        AdjustWindowRect(&mut window_dimension, WINDOW_STYLE, false);
+
+        match CreateWindowExW(
+            0,
+            class.as_ptr(),
+            title.as_ptr(),
+            WINDOW_STYLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            window_dimension.right - window_dimension.left,
+            window_dimension.bottom - window_dimension.top,
+            0,
+            0,
+            instance,
+            null(),
+        ) {
+            0 => return Err(PlatfromError::WindowHandleCreation),
+            x => return Ok(x),
+        };
+    }
+
+    unsafe extern "system" fn windows_message_callback(window: usize, message: u32, wparam: usize, lparam: usize) -> u32 {
+        match message {
+            WM_CLOSE => PostQuitMessage(0),
+            _ => return DefWindowProcW(window, message, wparam, lparam),
+        }
+
+        return 0;
+    }
+
+    // The hook is called from inside GetMessageW, which keeps waiting afterwards. Posting a message wakes it up.
+    unsafe extern "system" fn keyboard_hook(code: i32, wparam: usize, lparam: usize) -> usize {
+        let key_code = std::ptr::read(lparam as *const u32);
+        KEY_EVENTS.with(|events| events.borrow_mut().push_back(Event::KeyChanged(KeyCode::from_vk(key_code), Win32Platform::key_state_from_wparam(wparam))));
+        PostThreadMessageW(GetCurrentThreadId(), WM_APP, 0, 0);
+
+        return CallNextHookEx(0, code, wparam, lparam);
+    }
+
+    unsafe fn create_menu(items: &[MenuItem]) -> usize {
+        let menu = CreatePopupMenu();
+
+        for menu_item in items.iter() {
+            match menu_item {
+                MenuItem::Unchecked(name, id) => AppendMenuW(menu, MF_STRING, *id, to_unicode(name).as_ptr()),
+                MenuItem::Checked(name, id) => AppendMenuW(menu, MF_CHECKED, *id, to_unicode(name).as_ptr()),
+                MenuItem::Seperator => AppendMenuW(menu, MF_SEPARATOR, 0, null()),
+                MenuItem::SubMenu(name, items) => AppendMenuW(menu, MF_STRING | MF_POPUP, Win32Platform::create_menu(items) as u32, to_unicode(name).as_ptr()),
+            }
+        }
+
+        return menu;
+    }
+
+    fn show_menu(&self) -> u32 {
+        if self.menu == 0 {
+            return 0;
+        }
+
+        unsafe {
+            let mut cursor_pos: Point = zeroed();
+            GetCursorPos(&mut cursor_pos);
+            return TrackPopupMenu(self.menu, TPM_RETURNCMD, cursor_pos.x, cursor_pos.y, 0, self.window_handle, null());
+        }
+    }
+}
+
+impl Platform for Win32Platform {
+    fn next_event(&mut self) -> Event {
+        unsafe {
+            loop {
+                if let Some(event) = KEY_EVENTS.with(|events| events.borrow_mut().pop_front()) {
+                    return event;
+                }
+
+                if !self.running {
+                    return Event::Quit;
+                }
+
+                let mut message: Message = zeroed();
+                if !GetMessageW(&mut message, 0, 0, 0) {
+                    self.running = false;
+                    continue;
+                }
+
+                TranslateMessage(&mut message);
+                DispatchMessageW(&mut message);
+
+                match message.message {
+                    WM_RBUTTONUP => match self.show_menu() {
+                        0 => {}
+                        id => return Event::MenuCommand(id),
+                    },
+                    WM_TIMER if message.wparam == TIMER_ID => {
+                        KillTimer(self.window_handle, TIMER_ID);
+                        return Event::Timer;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn set_menu(&mut self, items: Vec<MenuItem>) {
+        unsafe {
+            if self.menu != 0 {
+                DestroyMenu(self.menu);
+            }
+            self.menu = Win32Platform::create_menu(&items);
+        }
+    }
+
+    fn set_timer(&mut self, timeout: Option<Duration>) {
+        unsafe {
+            match timeout {
+                Some(timeout) => SetTimer(self.window_handle, TIMER_ID, timeout.as_millis().max(1) as u32, null()),
+                None => KillTimer(self.window_handle, TIMER_ID) as usize,
+            };
+        }
+    }
+
+    fn time(&self) -> Duration {
+        return self.start.elapsed();
+    }
+
+    fn update_window(&mut self, buffer: &BackBuffer) {
+        let info = BitmapInfo {
+            header: BitmapInfoHeader {
+                size: size_of::<BitmapInfoHeader>() as u32,
+                width: buffer.width,
+                height: -buffer.height,
+                planes: 1,
+                bit_count: 32,
+                compression: BI_RGB,
+                size_image: 0,
+                x_pels_per_meter: 0,
+                y_pels_per_meter: 0,
+                colors_used: 0,
+                colors_important: 0,
+            },
+            colors: 0,
+        };
+
+        unsafe {
+            let dc = GetDC(self.window_handle);
+            SetStretchBltMode(dc, COLORONCOLOR);
+            StretchDIBits(
+                dc,
+                0,
+                0,
+                self.window_width,
+                self.window_height,
+                0,
+                0,
+                buffer.width,
+                buffer.height,
+                buffer.memory.as_ptr(),
+                &info,
+                DIB_RGB_COLORS,
+                SRCCOPY,
+            );
+            ReleaseDC(self.window_handle, dc);
+        }
+    }
+
+    fn set_scale(&mut self, scale: i32) -> Result<(), PlatfromError> {
+        let window_width = self.window_width / self.scale * scale;
+        let window_height = self.window_height / self.scale * scale;
+
+        unsafe {
+            let mut window_dimension = Rect { left: 0, top: 0, right: window_width, bottom: window_height };
+            AdjustWindowRect(&mut window_dimension, WINDOW_STYLE, false);
+            if !SetWindowPos(
+                self.window_handle,
+                0,
+                0,
+                0,
+                window_dimension.right - window_dimension.left,
+                window_dimension.bottom - window_dimension.top,
+                SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
+            ) {
+                return Err(PlatfromError::Unsupported);
+            }
+        }
+
+        self.window_width = window_width;
+        self.window_height = window_height;
+        self.scale = scale;
+        return Ok(());
+    }
+
+    fn is_key_down(&self, code: KeyCode) -> bool {
+        unsafe { return (GetKeyState(code.to_vk()) & 0x8000) > 0 };
+    }
+}
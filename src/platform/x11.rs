@@ -0,0 +1,668 @@
+use crate::gfx::*;
+use crate::linux::*;
+use crate::platform::evdev::*;
+use crate::platform::*;
+use crate::xlib::*;
+
+use std::collections::*;
+use std::mem::*;
+use std::os::raw::*;
+use std::ptr::*;
+
+pub struct X11Platform {
+    pub display: *mut Display,
+    pub screen: c_int,
+    pub window: Window,
+    pub gc: GC,
+    pub font: *mut XFontStruct,
+    pub wm_delete_window: Atom,
+    pub running: bool,
+    pub window_width: i32,
+    pub window_height: i32,
+    pub scale: i32,
+    pub events: VecDeque<Event>,
+    pub timer: Option<Instant>,
+    pub start: Instant,
+    pub keyboards: Option<EvdevKeyboards>,
+    pub keys_down: HashSet<KeyCode>,
+    pub framebuffer: Framebuffer,
+    menus: Vec<Vec<PopupEntry>>,
+}
+
+// Pixels are uploaded through MIT-SHM when the server is local and supports it, otherwise through a regular XPutImage.
+// Rows are bytes_per_line of the image apart, which can be more than the width of the window in pixels.
+pub enum Framebuffer {
+    Shared { image: *mut XImage, info: Box<XShmSegmentInfo> },
+    Plain { image: *mut XImage, pixels: Vec<u8> },
+}
+
+impl Framebuffer {
+    fn image(&self) -> *mut XImage {
+        match self {
+            Framebuffer::Shared { image, .. } => return *image,
+            Framebuffer::Plain { image, .. } => return *image,
+        }
+    }
+}
+
+// The back buffer is copied as it is, so the visual has to take its BGRA bytes as they are.
+unsafe fn is_bgra(image: *const XImage) -> bool {
+    return (*image).bits_per_pixel == 32 && (*image).byte_order == LSB_FIRST && (*image).red_mask == 0xff0000 && (*image).green_mask == 0xff00 && (*image).blue_mask == 0xff;
+}
+
+enum PopupEntry {
+    Command(String, bool, u32),
+    Seperator,
+    SubMenu(String, usize),
+}
+
+struct Popup {
+    menu: usize,
+    window: Window,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    hovered: Option<usize>,
+}
+
+const MENU_PADDING: i32 = 4;
+const MENU_GUTTER: i32 = 18;
+const MENU_SEPERATOR_HEIGHT: i32 = 7;
+const MENU_BACKGROUND: c_ulong = 0xf0f0f0;
+const MENU_HIGHLIGHT: c_ulong = 0x3399ff;
+const MENU_TEXT: c_ulong = 0x000000;
+const MENU_HIGHLIGHT_TEXT: c_ulong = 0xffffff;
+const MENU_SEPERATOR: c_ulong = 0xa0a0a0;
+
+static mut SHM_ERROR: bool = false;
+
+unsafe extern "C" fn shm_error_handler(_display: *mut Display, _event: *mut XErrorEvent) -> c_int {
+    SHM_ERROR = true;
+    return 0;
+}
+
+impl X11Platform {
+    pub fn new(width: i32, height: i32, scale: i32, title: &str, input_device: Option<&str>) -> Result<X11Platform, PlatfromError> {
+        unsafe {
+            let display = XOpenDisplay(null());
+            if display.is_null() {
+                return Err(PlatfromError::DisplayConnection);
+            }
+
+            let screen = XDefaultScreen(display);
+            let win_width = width * scale;
+            let win_height = height * scale;
+            let window = XCreateSimpleWindow(display, XRootWindow(display, screen), 0, 0, win_width as c_uint, win_height as c_uint, 0, XBlackPixel(display, screen), XBlackPixel(display, screen));
+            if window == 0 {
+                XCloseDisplay(display);
+                return Err(PlatfromError::WindowHandleCreation);
+            }
+
+            XStoreName(display, window, to_cstring(title).as_ptr());
+            XSelectInput(display, window, EXPOSURE_MASK | KEY_PRESS_MASK | KEY_RELEASE_MASK | BUTTON_PRESS_MASK | BUTTON_RELEASE_MASK);
+
+            X11Platform::fix_size(display, window, win_width, win_height);
+
+            let mut wm_delete_window = XInternAtom(display, to_cstring("WM_DELETE_WINDOW").as_ptr(), FALSE);
+            XSetWMProtocols(display, window, &mut wm_delete_window, 1);
+            XkbSetDetectableAutoRepeat(display, TRUE, null_mut());
+
+            let gc = XCreateGC(display, window, 0, null_mut());
+            let font = XLoadQueryFont(display, to_cstring("fixed").as_ptr());
+            if !font.is_null() {
+                XSetFont(display, gc, (*font).fid);
+            }
+
+            let framebuffer = match X11Platform::create_framebuffer(display, screen, win_width, win_height) {
+                Ok(framebuffer) => framebuffer,
+                Err(error) => {
+                    if !font.is_null() {
+                        XFreeFont(display, font);
+                    }
+                    XFreeGC(display, gc);
+                    XDestroyWindow(display, window);
+                    XCloseDisplay(display);
+                    return Err(error);
+                }
+            };
+
+            // Without access to /dev/input the display falls back to key events of the focused window.
+            let keyboards = match EvdevKeyboards::open(input_device) {
+                Ok(keyboards) => Some(keyboards),
+                Err(error) => {
+                    eprintln!("evdev input unavailable, only key presses in the window are shown: {}", error);
+                    None
+                }
+            };
+
+            XMapWindow(display, window);
+            XFlush(display);
+
+            return Ok(X11Platform {
+                display: display,
+                screen: screen,
+                window: window,
+                gc: gc,
+                font: font,
+                wm_delete_window: wm_delete_window,
+                running: true,
+                window_width: win_width,
+                window_height: win_height,
+                scale: scale,
+                events: VecDeque::new(),
+                timer: None,
+                start: Instant::now(),
+                keyboards: keyboards,
+                keys_down: HashSet::new(),
+                framebuffer: framebuffer,
+                menus: Vec::new(),
+            });
+        }
+    }
+
+    // The window can only be resized through the scale.
+    unsafe fn fix_size(display: *mut Display, window: Window, width: i32, height: i32) {
+        let mut hints: XSizeHints = zeroed();
+        hints.flags = P_MIN_SIZE | P_MAX_SIZE;
+        hints.min_width = width;
+        hints.max_width = width;
+        hints.min_height = height;
+        hints.max_height = height;
+        XSetWMNormalHints(display, window, &mut hints);
+    }
+
+    // Only visuals with 32 bit BGRA pixels are supported, other depths fail with Unsupported.
+    unsafe fn create_framebuffer(display: *mut Display, screen: c_int, width: i32, height: i32) -> Result<Framebuffer, PlatfromError> {
+        let visual = XDefaultVisual(display, screen);
+        let depth = XDefaultDepth(display, screen) as c_uint;
+
+        if XShmQueryExtension(display) != FALSE {
+            let mut info: Box<XShmSegmentInfo> = Box::new(zeroed());
+            let image = XShmCreateImage(display, visual, depth, Z_PIXMAP, null_mut(), &mut *info, width as c_uint, height as c_uint);
+            if !image.is_null() {
+                if !is_bgra(image) {
+                    XFree(image as *mut c_void);
+                    return Err(PlatfromError::Unsupported);
+                }
+
+                info.shmid = shmget(IPC_PRIVATE, ((*image).bytes_per_line * height) as usize, IPC_CREAT | 0o600);
+                if info.shmid >= 0 {
+                    info.shmaddr = shmat(info.shmid, null(), 0) as *mut c_char;
+                    (*image).data = info.shmaddr;
+
+                    // Attaching fails asynchronously on remote displays, so the error has to be caught after a round trip.
+                    SHM_ERROR = false;
+                    let previous_handler = XSetErrorHandler(Some(shm_error_handler));
+                    XShmAttach(display, &mut *info);
+                    XSync(display, FALSE);
+                    XSetErrorHandler(previous_handler);
+
+                    // The segment is freed as soon as both sides have detached from it.
+                    shmctl(info.shmid, IPC_RMID, null_mut());
+
+                    if !SHM_ERROR {
+                        return Ok(Framebuffer::Shared { image: image, info: info });
+                    }
+
+                    shmdt(info.shmaddr as *const c_void);
+                }
+
+                XFree(image as *mut c_void);
+            }
+        }
+
+        // Xlib works out the row size, the pixels are allocated once it is known.
+        let image = XCreateImage(display, visual, depth, Z_PIXMAP, 0, null_mut(), width as c_uint, height as c_uint, 32, 0);
+        if image.is_null() {
+            return Err(PlatfromError::Unsupported);
+        }
+        if !is_bgra(image) {
+            XFree(image as *mut c_void);
+            return Err(PlatfromError::Unsupported);
+        }
+
+        let mut pixels = vec![0; ((*image).bytes_per_line * height) as usize];
+        (*image).data = pixels.as_mut_ptr() as *mut c_char;
+        return Ok(Framebuffer::Plain { image: image, pixels: pixels });
+    }
+
+    unsafe fn destroy_framebuffer(&mut self) {
+        match &mut self.framebuffer {
+            Framebuffer::Shared { image, info } => {
+                XShmDetach(self.display, &mut **info);
+                shmdt(info.shmaddr as *const c_void);
+                XFree(*image as *mut c_void);
+            }
+            Framebuffer::Plain { image, .. } => {
+                XFree(*image as *mut c_void);
+            }
+        }
+    }
+
+    fn present(&mut self) {
+        unsafe {
+            match &self.framebuffer {
+                Framebuffer::Shared { image, .. } => {
+                    XShmPutImage(self.display, self.window, self.gc, *image, 0, 0, 0, 0, self.window_width as c_uint, self.window_height as c_uint, FALSE);
+                }
+                Framebuffer::Plain { image, .. } => {
+                    XPutImage(self.display, self.window, self.gc, *image, 0, 0, 0, 0, self.window_width as c_uint, self.window_height as c_uint);
+                }
+            }
+            XSync(self.display, FALSE);
+        }
+    }
+
+    fn handle_key(&mut self, code: KeyCode, key_state: KeyState) {
+        if key_state == KeyState::Pressed {
+            self.keys_down.insert(code);
+        } else {
+            self.keys_down.remove(&code);
+        }
+
+        self.events.push_back(Event::KeyChanged(code, key_state));
+    }
+
+    // Flattens the menu tree, the root ends up at index 0 and submenus refer to their entries by index.
+    fn add_menu(&mut self, items: &[MenuItem]) -> usize {
+        let index = self.menus.len();
+        self.menus.push(Vec::new());
+
+        for menu_item in items.iter() {
+            let entry = match menu_item {
+                MenuItem::Unchecked(name, id) => PopupEntry::Command(name.clone(), false, *id),
+                MenuItem::Checked(name, id) => PopupEntry::Command(name.clone(), true, *id),
+                MenuItem::Seperator => PopupEntry::Seperator,
+                MenuItem::SubMenu(name, items) => PopupEntry::SubMenu(name.clone(), self.add_menu(items)),
+            };
+            self.menus[index].push(entry);
+        }
+
+        return index;
+    }
+
+    unsafe fn show_menu(&self) -> u32 {
+        if self.menus.is_empty() {
+            return 0;
+        }
+
+        let mut root: Window = 0;
+        let mut child: Window = 0;
+        let (mut x, mut y, mut win_x, mut win_y, mut mask) = (0, 0, 0, 0, 0);
+        XQueryPointer(self.display, self.window, &mut root, &mut child, &mut x, &mut y, &mut win_x, &mut win_y, &mut mask);
+
+        return self.track_popup(0, x, y);
+    }
+
+    // Handles everything Xlib has already read, without blocking.
+    unsafe fn process_pending(&mut self) {
+        let mut event: XEvent = zeroed();
+
+        while self.running && XPending(self.display) > 0 {
+            XNextEvent(self.display, &mut event);
+
+            match event.kind() {
+                EXPOSE => self.present(),
+                // With evdev available the same presses already arrive from the device, regardless of focus.
+                KEY_PRESS | KEY_RELEASE if self.keyboards.is_none() => {
+                    let key_state = if event.kind() == KEY_PRESS { KeyState::Pressed } else { KeyState::Released };
+                    // Keysyms without a KeyCode, such as media keys, would look like an unbound key.
+                    if let Some(code) = KeyCode::from_keysym(XLookupKeysym(event.cast::<XKeyEvent>(), 0) as u32) {
+                        self.handle_key(code, key_state);
+                    }
+                }
+                BUTTON_RELEASE if event.cast::<XButtonEvent>().button == BUTTON_3 => match self.show_menu() {
+                    0 => {}
+                    id => self.events.push_back(Event::MenuCommand(id)),
+                },
+                CLIENT_MESSAGE if event.cast::<XClientMessageEvent>().data[0] as Atom == self.wm_delete_window => self.running = false,
+                _ => {}
+            }
+        }
+    }
+
+    fn text_width(&self, text: &str) -> i32 {
+        if self.font.is_null() {
+            return 6 * text.len() as i32;
+        }
+
+        return unsafe { XTextWidth(self.font, to_cstring(text).as_ptr(), text.len() as c_int) };
+    }
+
+    fn item_height(&self) -> i32 {
+        if self.font.is_null() {
+            return 13 + MENU_PADDING;
+        }
+
+        return unsafe { (*self.font).ascent + (*self.font).descent + MENU_PADDING };
+    }
+
+    fn entry_height(&self, entry: &PopupEntry) -> i32 {
+        match entry {
+            PopupEntry::Seperator => MENU_SEPERATOR_HEIGHT,
+            _ => self.item_height(),
+        }
+    }
+
+    fn entry_at(&self, popup: &Popup, y_root: i32) -> Option<usize> {
+        let mut y = popup.y + 1;
+
+        for (i, entry) in self.menus[popup.menu].iter().enumerate() {
+            let height = self.entry_height(entry);
+            if y_root >= y && y_root < y + height {
+                return Some(i);
+            }
+            y += height;
+        }
+
+        return None;
+    }
+
+    unsafe fn open_popup(&self, menu: usize, x: i32, y: i32) -> Popup {
+        let entries = &self.menus[menu];
+
+        let mut width = 0;
+        let mut height = 2;
+        for entry in entries.iter() {
+            width = match entry {
+                PopupEntry::Command(name, _, _) | PopupEntry::SubMenu(name, _) => width.max(self.text_width(name)),
+                PopupEntry::Seperator => width,
+            };
+            height += self.entry_height(entry);
+        }
+        width += MENU_GUTTER * 2 + 2;
+
+        let x = x.min(XDisplayWidth(self.display, self.screen) - width).max(0);
+        let y = y.min(XDisplayHeight(self.display, self.screen) - height).max(0);
+
+        let mut attributes: XSetWindowAttributes = zeroed();
+        attributes.override_redirect = TRUE;
+        attributes.save_under = TRUE;
+        attributes.background_pixel = MENU_BACKGROUND;
+        attributes.border_pixel = MENU_SEPERATOR;
+        attributes.event_mask = EXPOSURE_MASK;
+
+        let window = XCreateWindow(
+            self.display,
+            XRootWindow(self.display, self.screen),
+            x,
+            y,
+            width as c_uint,
+            height as c_uint,
+            0,
+            COPY_FROM_PARENT,
+            INPUT_OUTPUT,
+            null_mut(),
+            CW_OVERRIDE_REDIRECT | CW_SAVE_UNDER | CW_BACK_PIXEL | CW_BORDER_PIXEL | CW_EVENT_MASK,
+            &mut attributes,
+        );
+        XMapRaised(self.display, window);
+
+        return Popup { menu: menu, window: window, x: x, y: y, width: width, height: height, hovered: None };
+    }
+
+    unsafe fn draw_popup(&self, popup: &Popup) {
+        let ascent = if self.font.is_null() { 10 } else { (*self.font).ascent };
+
+        XSetForeground(self.display, self.gc, MENU_BACKGROUND);
+        XFillRectangle(self.display, popup.window, self.gc, 0, 0, popup.width as c_uint, popup.height as c_uint);
+        XSetForeground(self.display, self.gc, MENU_SEPERATOR);
+        XDrawLine(self.display, popup.window, self.gc, 0, 0, popup.width - 1, 0);
+        XDrawLine(self.display, popup.window, self.gc, 0, popup.height - 1, popup.width - 1, popup.height - 1);
+        XDrawLine(self.display, popup.window, self.gc, 0, 0, 0, popup.height - 1);
+        XDrawLine(self.display, popup.window, self.gc, popup.width - 1, 0, popup.width - 1, popup.height - 1);
+
+        let mut y = 1;
+        for (i, entry) in self.menus[popup.menu].iter().enumerate() {
+            let height = self.entry_height(entry);
+            let text_color = if popup.hovered == Some(i) {
+                XSetForeground(self.display, self.gc, MENU_HIGHLIGHT);
+                XFillRectangle(self.display, popup.window, self.gc, 1, y, (popup.width - 2) as c_uint, height as c_uint);
+                MENU_HIGHLIGHT_TEXT
+            } else {
+                MENU_TEXT
+            };
+
+            let baseline = y + MENU_PADDING / 2 + ascent;
+            match entry {
+                PopupEntry::Command(name, checked, _) => {
+                    XSetForeground(self.display, self.gc, text_color);
+                    if *checked {
+                        XFillRectangle(self.display, popup.window, self.gc, MENU_GUTTER / 2 - 2, y + height / 2 - 2, 5, 5);
+                    }
+                    XDrawString(self.display, popup.window, self.gc, MENU_GUTTER, baseline, to_cstring(name).as_ptr(), name.len() as c_int);
+                }
+                PopupEntry::SubMenu(name, _) => {
+                    XSetForeground(self.display, self.gc, text_color);
+                    XDrawString(self.display, popup.window, self.gc, MENU_GUTTER, baseline, to_cstring(name).as_ptr(), name.len() as c_int);
+                    XDrawString(self.display, popup.window, self.gc, popup.width - MENU_GUTTER + 4, baseline, to_cstring(">").as_ptr(), 1);
+                }
+                PopupEntry::Seperator => {
+                    XSetForeground(self.display, self.gc, MENU_SEPERATOR);
+                    XDrawLine(self.display, popup.window, self.gc, 1, y + height / 2, popup.width - 2, y + height / 2);
+                }
+            }
+
+            y += height;
+        }
+    }
+
+    // Runs a modal loop until an entry is picked or the menu is dismissed. Returns 0 when nothing was picked.
+    // Events for the main window that arrive in the meantime are put back afterwards, in their original order.
+    unsafe fn track_popup(&self, menu: usize, x: i32, y: i32) -> u32 {
+        let mut popups = vec![self.open_popup(menu, x, y)];
+        let event_mask = (BUTTON_PRESS_MASK | BUTTON_RELEASE_MASK | POINTER_MOTION_MASK) as c_uint;
+        if XGrabPointer(self.display, popups[0].window, FALSE, event_mask, GRAB_MODE_ASYNC, GRAB_MODE_ASYNC, 0, 0, CURRENT_TIME) != GRAB_SUCCESS {
+            XDestroyWindow(self.display, popups[0].window);
+            return 0;
+        }
+
+        let mut result = 0;
+        let mut event: XEvent = zeroed();
+        let mut deferred: Vec<XEvent> = Vec::new();
+        loop {
+            XNextEvent(self.display, &mut event);
+            match event.kind() {
+                EXPOSE => {
+                    let window = event.cast::<XAnyEvent>().window;
+                    match popups.iter().find(|popup| popup.window == window) {
+                        Some(popup) => self.draw_popup(popup),
+                        None => deferred.push(event),
+                    }
+                }
+                KEY_PRESS | KEY_RELEASE | CLIENT_MESSAGE => deferred.push(event),
+                MOTION_NOTIFY => {
+                    let motion = event.cast::<XMotionEvent>();
+                    let target = popups.iter().rposition(|popup| motion.x_root >= popup.x && motion.x_root < popup.x + popup.width && motion.y_root >= popup.y && motion.y_root < popup.y + popup.height);
+                    if let Some(index) = target {
+                        let hovered = self.entry_at(&popups[index], motion.y_root);
+                        if hovered != popups[index].hovered {
+                            for popup in popups.drain(index + 1..) {
+                                XDestroyWindow(self.display, popup.window);
+                            }
+                            popups[index].hovered = hovered;
+                            self.draw_popup(&popups[index]);
+
+                            let mut submenu = None;
+                            let mut entry_y = popups[index].y;
+                            if let Some(hovered) = hovered {
+                                let entries = &self.menus[popups[index].menu];
+                                if let PopupEntry::SubMenu(_, id) = entries[hovered] {
+                                    submenu = Some(id);
+                                }
+                                entry_y += entries.iter().take(hovered).map(|entry| self.entry_height(entry)).sum::<i32>();
+                            }
+
+                            if let Some(submenu) = submenu {
+                                let child = self.open_popup(submenu, popups[index].x + popups[index].width, entry_y);
+                                popups.push(child);
+                            }
+                        }
+                    }
+                }
+                BUTTON_PRESS => {
+                    let button = event.cast::<XButtonEvent>();
+                    if !popups.iter().any(|popup| button.x_root >= popup.x && button.x_root < popup.x + popup.width && button.y_root >= popup.y && button.y_root < popup.y + popup.height) {
+                        break;
+                    }
+                }
+                BUTTON_RELEASE => {
+                    let button = event.cast::<XButtonEvent>();
+                    let target = popups.iter().rposition(|popup| button.x_root >= popup.x && button.x_root < popup.x + popup.width && button.y_root >= popup.y && button.y_root < popup.y + popup.height);
+                    if let Some(index) = target {
+                        if let Some(entry) = self.entry_at(&popups[index], button.y_root) {
+                            if let PopupEntry::Command(_, _, id) = self.menus[popups[index].menu][entry] {
+                                result = id;
+                                break;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        XUngrabPointer(self.display, CURRENT_TIME);
+        for popup in popups.iter() {
+            XDestroyWindow(self.display, popup.window);
+        }
+        XFlush(self.display);
+
+        for mut event in deferred.into_iter().rev() {
+            XPutBackEvent(self.display, &mut event);
+        }
+
+        return result;
+    }
+}
+
+impl Platform for X11Platform {
+    fn next_event(&mut self) -> Event {
+        unsafe {
+            loop {
+                if let Some(event) = self.events.pop_front() {
+                    return event;
+                }
+
+                self.process_pending();
+
+                if !self.running {
+                    return Event::Quit;
+                }
+
+                if !self.events.is_empty() {
+                    continue;
+                }
+
+                if let Some(deadline) = self.timer {
+                    if Instant::now() >= deadline {
+                        self.timer = None;
+                        return Event::Timer;
+                    }
+                }
+
+                let timeout = self.timer.map_or(-1, |deadline| deadline.saturating_duration_since(Instant::now()).as_millis().max(1) as c_int);
+                let mut fds = vec![PollFd { fd: XConnectionNumber(self.display), events: POLLIN, revents: 0 }];
+                if let Some(keyboards) = &self.keyboards {
+                    fds.extend(keyboards.poll_fds());
+                }
+                poll(fds.as_mut_ptr(), fds.len() as c_ulong, timeout);
+
+                if let Some(keyboards) = &mut self.keyboards {
+                    for (code, key_state) in keyboards.read_events() {
+                        self.handle_key(code, key_state);
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_menu(&mut self, items: Vec<MenuItem>) {
+        self.menus.clear();
+        self.add_menu(&items);
+    }
+
+    fn set_timer(&mut self, timeout: Option<Duration>) {
+        self.timer = timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    fn time(&self) -> Duration {
+        return self.start.elapsed();
+    }
+
+    fn update_window(&mut self, buffer: &BackBuffer) {
+        let row_size = unsafe { (*self.framebuffer.image()).bytes_per_line } as usize;
+        let pixels = match &mut self.framebuffer {
+            Framebuffer::Shared { info, .. } => unsafe { std::slice::from_raw_parts_mut(info.shmaddr as *mut u8, row_size * self.window_height as usize) },
+            Framebuffer::Plain { pixels, .. } => pixels.as_mut_slice(),
+        };
+
+        // Nearest neighbour upscaling, every source pixel becomes a scale x scale block.
+        for y in 0..self.window_height {
+            let src_row = ((y / self.scale) * buffer.width * BYTES_PER_PIXEL) as usize;
+            let dest_row = y as usize * row_size;
+            for x in 0..self.window_width {
+                let src = src_row + ((x / self.scale) * BYTES_PER_PIXEL) as usize;
+                let dest = dest_row + (x * BYTES_PER_PIXEL) as usize;
+                pixels[dest..dest + BYTES_PER_PIXEL as usize].copy_from_slice(&buffer.memory[src..src + BYTES_PER_PIXEL as usize]);
+            }
+        }
+
+        self.present();
+    }
+
+    // The framebuffer is recreated at the new size, the caller redraws into it. When that fails the window keeps its size.
+    fn set_scale(&mut self, scale: i32) -> Result<(), PlatfromError> {
+        let window_width = self.window_width / self.scale * scale;
+        let window_height = self.window_height / self.scale * scale;
+
+        unsafe {
+            // The old framebuffer is kept until the new one exists, so a failure leaves the window as it was.
+            let framebuffer = X11Platform::create_framebuffer(self.display, self.screen, window_width, window_height)?;
+
+            self.destroy_framebuffer();
+            self.framebuffer = framebuffer;
+            self.window_width = window_width;
+            self.window_height = window_height;
+            self.scale = scale;
+
+            X11Platform::fix_size(self.display, self.window, self.window_width, self.window_height);
+            XResizeWindow(self.display, self.window, self.window_width as c_uint, self.window_height as c_uint);
+            XFlush(self.display);
+        }
+        return Ok(());
+    }
+
+    fn is_key_down(&self, code: KeyCode) -> bool {
+        if self.keyboards.is_some() {
+            return self.keys_down.contains(&code);
+        }
+
+        unsafe {
+            let keycode = XKeysymToKeycode(self.display, code.to_keysym() as KeySym) as usize;
+            if keycode == 0 {
+                return false;
+            }
+
+            let mut keys = [0 as c_char; 32];
+            XQueryKeymap(self.display, keys.as_mut_ptr());
+            return (keys[keycode / 8] as u8 & (1 << (keycode % 8))) > 0;
+        }
+    }
+}
+
+impl Drop for X11Platform {
+    fn drop(&mut self) {
+        unsafe {
+            self.destroy_framebuffer();
+
+            if !self.font.is_null() {
+                XFreeFont(self.display, self.font);
+            }
+            XFreeGC(self.display, self.gc);
+            XDestroyWindow(self.display, self.window);
+            XCloseDisplay(self.display);
+        }
+    }
+}
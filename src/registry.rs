@@ -0,0 +1,61 @@
+#[cfg(windows)]
+use crate::winapi::*;
+
+use std::io::*;
+
+// Thin wrapper around a subkey of HKEY_CURRENT_USER. The handle is closed when dropped.
+pub struct RegistryKey {
+    #[cfg_attr(not(windows), allow(dead_code))]
+    hkey: usize,
+}
+
+#[cfg(windows)]
+macro_rules! werr {
+    ($e:expr) => {
+        Err(Error::from_raw_os_error($e as i32))
+    };
+}
+
+#[cfg(windows)]
+impl RegistryKey {
+    pub fn open(sub_key: &str) -> Result<RegistryKey> {
+        unsafe {
+            let mut new_hkey: usize = 0;
+            match RegOpenKeyExW(HKEY_CURRENT_USER, to_unicode(sub_key).as_ptr(), 0, KEY_QUERY_VALUE, &mut new_hkey) {
+                0 => Ok(RegistryKey { hkey: new_hkey }),
+                err => werr!(err),
+            }
+        }
+    }
+
+    pub fn read_u32(&self, name: &str) -> Result<u32> {
+        unsafe {
+            let mut buf: u32 = 0;
+            let mut buf_len = std::mem::size_of::<u32>() as u32;
+            let mut buf_type = 0;
+            match RegQueryValueExW(self.hkey, to_unicode(name).as_ptr(), std::ptr::null(), &mut buf_type, &mut buf as *mut u32 as *mut u8, &mut buf_len) {
+                0 => Ok(buf),
+                err => werr!(err),
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for RegistryKey {
+    fn drop(&mut self) {
+        unsafe { RegCloseKey(self.hkey) };
+    }
+}
+
+// There is no registry outside of Windows, every access reports as unsupported.
+#[cfg(not(windows))]
+impl RegistryKey {
+    pub fn open(_sub_key: &str) -> Result<RegistryKey> {
+        return Err(Error::from(ErrorKind::Unsupported));
+    }
+
+    pub fn read_u32(&self, _name: &str) -> Result<u32> {
+        return Err(Error::from(ErrorKind::Unsupported));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::bmp::*;
+use crate::gfx::*;
+use crate::ini::*;
+use crate::layout::*;
+
+use std::path::*;
+
+pub const KEY_SIZE: i32 = 34;
+pub const ARROW_SIZE: i32 = 14;
+pub const CHARACTER_SIZE: i32 = 16;
+
+// The file in a skin directory that gives the sprite size of each sheet, e.g. "size = 34, 34" in its [keys] section.
+// "true_color = true" in its [skin] section draws the sheets in their own colours instead of the selected palette.
+pub const SKIN_MANIFEST: &str = "skin.ini";
+const SKIN_SECTION: &str = "skin";
+
+// How far each glyph of the embedded font advances the text, in the order of the sheet.
+const EMBEDDED_GLYPH_WIDTHS: [i32; GLYPH_COUNT as usize] = [
+    8, 4, 8, 12, 12, 14, 14, 4, 8, 8, 12, 12, 6, 10, 4, 12,
+    12, 12, 12, 12, 12, 12, 12, 12, 12, 12, 4, 6, 10, 12, 10, 12,
+    14, 16, 16, 16, 16, 16, 16, 16, 16, 14, 16, 16, 16, 16, 16, 16,
+    16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 8, 12, 8, 12, 12,
+    6, 12, 12, 12, 12, 12, 10, 12, 12, 4, 10, 10, 8, 12, 12, 12,
+    12, 12, 10, 12, 10, 12, 12, 12, 12, 12, 12, 10, 4, 10, 14, 12,
+];
+
+// The sprite sheets everything is drawn with, either the embedded ones or those of a skin directory.
+pub struct Skin {
+    pub keyset: SpriteSheet,
+    pub arrowset: SpriteSheet,
+    pub font: Font,
+}
+
+impl Skin {
+    pub fn embedded() -> Skin {
+        return Skin {
+            keyset: embedded_sheet(include_bytes!("gfx/keys.bmp"), "keys.bmp", KEY_SIZE),
+            arrowset: embedded_sheet(include_bytes!("gfx/arrows.bmp"), "arrows.bmp", ARROW_SIZE),
+            font: Font::new(embedded_sheet(include_bytes!("gfx/font.bmp"), "font.bmp", CHARACTER_SIZE), EMBEDDED_GLYPH_WIDTHS.to_vec()),
+        };
+    }
+
+    // Reads keys.bmp, arrows.bmp and font.bmp from the directory, each sheet sized by its section of the manifest.
+    // The glyphs of the font are as wide as its sprites unless [font] lists their widths, e.g. "widths = 8, 4, 8, ...".
+    pub fn from_directory(directory: &Path) -> Result<Skin, String> {
+        let manifest = std::fs::read_to_string(directory.join(SKIN_MANIFEST)).map_err(|error| format!("{}: {}", SKIN_MANIFEST, error))?;
+        let ini = Ini::parse(&manifest);
+        let embedded = Skin::embedded();
+        let true_color = ini.get(SKIN_SECTION, "true_color") == Some("true");
+
+        return Ok(Skin {
+            keyset: load_sheet(directory, &ini, "keys", &embedded.keyset, true_color)?,
+            arrowset: load_sheet(directory, &ini, "arrows", &embedded.arrowset, true_color)?,
+            font: Font::new(load_sheet(directory, &ini, "font", &embedded.font.sheet, true_color)?, glyph_widths(&ini)?),
+        });
+    }
+
+    // Falls back to the embedded art when the skin cannot be used.
+    pub fn load(directory: Option<&str>) -> Skin {
+        let directory = match directory {
+            Some(directory) => directory,
+            None => return Skin::embedded(),
+        };
+
+        match Skin::from_directory(Path::new(directory)) {
+            Ok(skin) => return skin,
+            Err(error) => {
+                eprintln!("{}: {}, using the embedded skin", directory, error);
+                return Skin::embedded();
+            }
+        }
+    }
+}
+
+fn embedded_sheet(data: &[u8], file_name: &str, sprite_size: i32) -> SpriteSheet {
+    match bmp_load(data) {
+        Ok(bitmap) => return SpriteSheet::new(bitmap, sprite_size, sprite_size),
+        Err(error) => panic!("{}", error.with_file_name(file_name)),
+    }
+}
+
+fn glyph_widths(manifest: &Ini) -> Result<Vec<i32>, String> {
+    return match manifest.get("font", "widths") {
+        Some(widths) => parse_list::<i32>(widths).filter(|widths| widths.iter().all(|width| *width >= 0)).ok_or_else(|| format!("{} [font] has invalid widths", SKIN_MANIFEST)),
+        None => Ok(Vec::new()),
+    };
+}
+
+// Layouts refer to sprites by index, so a sheet has to hold at least as many rows and columns of sprites as the embedded one.
+fn load_sheet(directory: &Path, manifest: &Ini, name: &str, embedded: &SpriteSheet, true_color: bool) -> Result<SpriteSheet, String> {
+    let file_name = format!("{}.bmp", name);
+    let bitmap = bmp_load_file(&directory.join(&file_name), true_color).map_err(|error| error.to_string())?;
+
+    let size = manifest.get(name, "size").ok_or_else(|| format!("{} [{}] is missing size", SKIN_MANIFEST, name))?;
+    let (sprite_width, sprite_height) = match parse_list::<i32>(size).as_deref() {
+        Some([width, height]) if *width > 0 && *height > 0 => (*width, *height),
+        _ => return Err(format!("{} [{}] has an invalid size", SKIN_MANIFEST, name)),
+    };
+
+    let columns = embedded.sprites_per_row;
+    let rows = embedded.bitmap.height / embedded.sprite_height;
+    if bitmap.width / sprite_width < columns || bitmap.height / sprite_height < rows {
+        return Err(format!("{} needs at least {}x{} sprites of {}x{} pixels", file_name, columns, rows, sprite_width, sprite_height));
+    }
+
+    return Ok(SpriteSheet::new(bitmap, sprite_width, sprite_height));
+}
@@ -0,0 +1,187 @@
+use crate::application::*;
+use crate::configuration::*;
+
+use std::io::*;
+use std::path::*;
+use std::time::*;
+
+// Each session is written to this directory next to the configuration file, as session-<seconds since 1970>.json and .csv.
+const STATS_DIRECTORY: &str = "stats";
+
+// What a key has done since the session started or the statistics were last reset.
+#[derive(Default)]
+pub struct KeyStats {
+    pub presses: u64,
+    pub held: Duration,
+    pub longest: Duration,
+    pub pressed_at: Option<Duration>,
+}
+
+impl KeyStats {
+    // Key repeats of a held key are not presses of their own.
+    pub fn press(&mut self, time: Duration) {
+        if self.pressed_at.is_none() {
+            self.presses += 1;
+            self.pressed_at = Some(time);
+        }
+    }
+
+    pub fn release(&mut self, time: Duration) {
+        if let Some(pressed_at) = self.pressed_at.take() {
+            let hold = time.saturating_sub(pressed_at);
+            self.held += hold;
+            self.longest = self.longest.max(hold);
+        }
+    }
+
+    // Counts a hold that is still going on up to now.
+    pub fn current(&self, now: Duration) -> (Duration, Duration) {
+        let hold = self.pressed_at.map_or(Duration::ZERO, |pressed_at| now.saturating_sub(pressed_at));
+        return (self.held + hold, self.longest.max(hold));
+    }
+}
+
+pub fn count_key_state(app: &mut Application, key: usize, pressed: bool) {
+    let now = app.platform.time();
+    if pressed {
+        app.keys[key].stats.press(now);
+    } else {
+        app.keys[key].stats.release(now);
+    }
+}
+
+// Keys that are held keep being held, but only from now on.
+pub fn reset_stats(app: &mut Application) {
+    let now = app.platform.time();
+    for key in app.keys.iter_mut() {
+        let pressed_at = key.stats.pressed_at.map(|_| now);
+        key.stats = KeyStats { pressed_at: pressed_at, ..KeyStats::default() };
+    }
+
+    app.stats_since = now;
+    draw_background(app);
+}
+
+pub fn toggle_press_counts(app: &mut Application) {
+    app.show_press_counts = !app.show_press_counts;
+    update_menu(app);
+    draw_background(app);
+}
+
+fn json_string(text: &str) -> String {
+    return format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        return format!("\"{}\"", text.replace('"', "\"\""));
+    }
+
+    return text.to_string();
+}
+
+// Times are in seconds.
+pub fn stats_json(app: &Application) -> String {
+    let now = app.platform.time();
+    let mut keys: Vec<String> = Vec::new();
+
+    for key in app.keys.iter() {
+        let (held, longest) = key.stats.current(now);
+        keys.push(format!("    {{ \"name\": {}, \"presses\": {}, \"held\": {:.3}, \"longest\": {:.3} }}", json_string(&key.name), key.stats.presses, held.as_secs_f64(), longest.as_secs_f64()));
+    }
+
+    return format!("{{\n  \"duration\": {:.3},\n  \"keys\": [\n{}\n  ]\n}}\n", now.saturating_sub(app.stats_since).as_secs_f64(), keys.join(",\n"));
+}
+
+pub fn stats_csv(app: &Application) -> String {
+    let now = app.platform.time();
+    let mut csv = String::from("key,presses,held,longest\n");
+
+    for key in app.keys.iter() {
+        let (held, longest) = key.stats.current(now);
+        csv += &format!("{},{},{:.3},{:.3}\n", csv_field(&key.name), key.stats.presses, held.as_secs_f64(), longest.as_secs_f64());
+    }
+
+    return csv;
+}
+
+fn stats_directory() -> Option<PathBuf> {
+    return configuration_path().and_then(|path| path.parent().map(|directory| directory.join(STATS_DIRECTORY)));
+}
+
+// Sessions without a single press are not written.
+pub fn export_stats(app: &Application) -> Result<()> {
+    if app.keys.iter().all(|key| key.stats.presses == 0) {
+        return Ok(());
+    }
+
+    let directory = stats_directory().ok_or_else(|| Error::new(ErrorKind::NotFound, "no configuration directory"))?;
+    let name = format!("session-{}", SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs()));
+
+    std::fs::create_dir_all(&directory)?;
+    std::fs::write(directory.join(format!("{}.json", name)), stats_json(app))?;
+    std::fs::write(directory.join(format!("{}.csv", name)), stats_csv(app))?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::*;
+    use crate::layout::*;
+    use crate::platform::*;
+    use crate::skin::*;
+
+    const LAYOUT: &str = "[layout]\nsize = 3, 1\ntext = 0.5\n[a]\nposition = 0, 0\nsprite = 0\n[\"q\", \\]\nposition = 1, 0\nsprite = 1\n[held]\nposition = 2, 0\nsprite = 2\n";
+
+    fn seconds(seconds: f64) -> Duration {
+        return Duration::from_secs_f64(seconds);
+    }
+
+    // A pressed twice, the key with the awkward name never, HELD since 3 seconds and still down at 4.5 seconds.
+    fn session() -> Application {
+        let mut platform = HeadlessPlatform::new(Vec::new());
+        platform.time = seconds(4.5);
+        let mut app = Application::new(Box::new(platform), &Layout::parse(LAYOUT).unwrap(), Skin::load(None), InputHistory::new(false, DEFAULT_HISTORY_LENGTH, DEFAULT_HISTORY_SPEED));
+        app.stats_since = seconds(0.5);
+
+        let stats = &mut app.keys[0].stats;
+        stats.press(seconds(1.0));
+        stats.press(seconds(1.1));
+        stats.release(seconds(1.25));
+        stats.press(seconds(2.0));
+        stats.release(seconds(2.5));
+        stats.release(seconds(2.6));
+        app.keys[2].stats.press(seconds(3.0));
+        return app;
+    }
+
+    #[test]
+    fn holds_are_counted_up_to_now() {
+        let app = session();
+        assert_eq!(app.keys.iter().map(|key| key.stats.presses).collect::<Vec<u64>>(), vec![2, 0, 1]);
+        assert_eq!(app.keys[0].stats.current(seconds(9.0)), (seconds(0.75), seconds(0.5)));
+        assert_eq!(app.keys[2].stats.current(seconds(4.5)), (seconds(1.5), seconds(1.5)));
+    }
+
+    #[test]
+    fn sessions_are_written_as_json() {
+        let expected = concat!(
+            "{\n",
+            "  \"duration\": 4.000,\n",
+            "  \"keys\": [\n",
+            "    { \"name\": \"A\", \"presses\": 2, \"held\": 0.750, \"longest\": 0.500 },\n",
+            "    { \"name\": \"\\\"Q\\\", \\\\\", \"presses\": 0, \"held\": 0.000, \"longest\": 0.000 },\n",
+            "    { \"name\": \"HELD\", \"presses\": 1, \"held\": 1.500, \"longest\": 1.500 }\n",
+            "  ]\n",
+            "}\n",
+        );
+        assert_eq!(stats_json(&session()), expected);
+    }
+
+    #[test]
+    fn sessions_are_written_as_csv() {
+        assert_eq!(stats_csv(&session()), "key,presses,held,longest\nA,2,0.750,0.500\n\"\"\"Q\"\", \\\",0,0.000,0.000\nHELD,1,1.500,1.500\n");
+        assert_eq!(csv_field("LINE\nBREAK"), "\"LINE\nBREAK\"");
+    }
+}
@@ -76,6 +76,7 @@ pub fn to_unicode(s: &str) -> Vec<u16> {
 extern "C" {
     pub fn RegisterClassW(window_class: &WindowClassW) -> u16;
     pub fn AdjustWindowRect(rect: &mut Rect, style: u32, menu: bool);
+    pub fn SetWindowPos(window: usize, insert_after: usize, x: i32, y: i32, width: i32, height: i32, flags: u32) -> bool;
     pub fn CreateWindowExW(
         extended_style: u32,
         class_name: *const u16,
@@ -96,15 +97,20 @@ extern "C" {
     pub fn TranslateMessage(message: &mut Message);
     pub fn DispatchMessageW(message: &mut Message);
     pub fn PostQuitMessage(exit_code: i32);
+    pub fn PostThreadMessageW(thread_id: u32, message: u32, wparam: usize, lparam: usize) -> bool;
 
     pub fn SetWindowsHookExW(hook_type: u32, callback: HookCallback, instance: usize, thread_id: u32) -> usize;
     pub fn CallNextHookEx(hook: usize, code: i32, wparam: usize, lparam: usize) -> usize;
 
+    pub fn SetTimer(window: usize, id: usize, elapse: u32, callback: *const u8) -> usize;
+    pub fn KillTimer(window: usize, id: usize) -> bool;
+
     pub fn GetDC(window: usize) -> usize;
     pub fn ReleaseDC(window: usize, device_context: usize);
 
     pub fn CreatePopupMenu() -> usize;
     pub fn AppendMenuW(menu: usize, flags: u32, id: u32, name: *const u16);
+    pub fn DestroyMenu(menu: usize) -> bool;
     pub fn TrackPopupMenu(menu: usize, flags: u32, x: i32, y: i32, reserved: i32, window: usize, reserved: *const Rect) -> u32;
 
     pub fn GetCursorPos(point: &mut Point);
@@ -128,12 +134,14 @@ extern "C" {
         usage: u32,
         rop: u32,
     );
+    pub fn SetStretchBltMode(device_context: usize, mode: i32) -> i32;
 }
 
 #[link(name = "kernel32")]
 extern "C" {
     pub fn GetModuleHandleW(module_name: *const u16) -> usize;
     pub fn GetLastError() -> u32;
+    pub fn GetCurrentThreadId() -> u32;
 }
 
 #[link(name = "advapi32")]
@@ -240,6 +248,7 @@ pub const WM_SYSKEYDOWN: u32 = 0x0104;
 pub const WM_SYSKEYUP: u32 = 0x0105;
 pub const WM_SYSCHAR: u32 = 0x0106;
 pub const WM_SYSDEADCHAR: u32 = 0x0107;
+pub const WM_TIMER: u32 = 0x0113;
 pub const WM_MOUSEFIRST: u32 = 0x0200;
 pub const WM_MOUSEMOVE: u32 = 0x0200;
 pub const WM_LBUTTONDOWN: u32 = 0x0201;
@@ -251,6 +260,7 @@ pub const WM_RBUTTONDBLCLK: u32 = 0x0206;
 pub const WM_MBUTTONDOWN: u32 = 0x0207;
 pub const WM_MBUTTONUP: u32 = 0x0208;
 pub const WM_MBUTTONDBLCLK: u32 = 0x0209;
+pub const WM_APP: u32 = 0x8000;
 
 pub const WH_JOURNALRECORD: u32 = 0;
 pub const WH_JOURNALPLAYBACK: u32 = 1;
@@ -273,6 +283,13 @@ pub const BI_BITFIELDS: u32 = 3;
 pub const BI_JPEG: u32 = 4;
 pub const BI_PNG: u32 = 5;
 
+pub const COLORONCOLOR: i32 = 3;
+
+pub const SWP_NOSIZE: u32 = 0x0001;
+pub const SWP_NOMOVE: u32 = 0x0002;
+pub const SWP_NOZORDER: u32 = 0x0004;
+pub const SWP_NOACTIVATE: u32 = 0x0010;
+
 pub const DIB_RGB_COLORS: u32 = 0;
 pub const DIB_PAL_COLORS: u32 = 1;
 
@@ -336,8 +353,6 @@ pub const TPM_NOANIMATION: u32 = 0x4000;
 pub const TPM_LAYOUTRTL: u32 = 0x8000;
 pub const TPM_WORKAREA: u32 = 0x10000;
 
-pub const VK_CONTROL: u32 = 162;
-
 pub const HKEY_CLASSES_ROOT: usize = 0x80000000;
 pub const HKEY_CURRENT_USER: usize = 0x80000001;
 pub const HKEY_LOCAL_MACHINE: usize = 0x80000002;
@@ -0,0 +1,351 @@
+#![allow(dead_code)]
+
+use std::os::raw::*;
+
+pub type Display = c_void;
+pub type Visual = c_void;
+pub type GC = *mut c_void;
+pub type Window = c_ulong;
+pub type Drawable = c_ulong;
+pub type Atom = c_ulong;
+pub type KeySym = c_ulong;
+pub type XKeyCode = c_uchar;
+pub type Bool = c_int;
+pub type Status = c_int;
+pub type XErrorHandler = unsafe extern "C" fn(*mut Display, *mut XErrorEvent) -> c_int;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XEvent {
+    pub pad: [c_long; 24],
+}
+
+impl XEvent {
+    pub fn kind(&self) -> c_int {
+        return unsafe { (*(self as *const XEvent as *const XAnyEvent)).kind };
+    }
+
+    pub unsafe fn cast<T>(&self) -> &T {
+        return &*(self as *const XEvent as *const T);
+    }
+}
+
+#[repr(C)]
+pub struct XAnyEvent {
+    pub kind: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+}
+
+#[repr(C)]
+pub struct XKeyEvent {
+    pub kind: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: c_ulong,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub keycode: c_uint,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+pub struct XButtonEvent {
+    pub kind: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: c_ulong,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub button: c_uint,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+pub struct XMotionEvent {
+    pub kind: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: c_ulong,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub is_hint: c_char,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+pub struct XClientMessageEvent {
+    pub kind: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub message_type: Atom,
+    pub format: c_int,
+    pub data: [c_long; 5],
+}
+
+#[repr(C)]
+pub struct XErrorEvent {
+    pub kind: c_int,
+    pub display: *mut Display,
+    pub resourceid: c_ulong,
+    pub serial: c_ulong,
+    pub error_code: c_uchar,
+    pub request_code: c_uchar,
+    pub minor_code: c_uchar,
+}
+
+#[repr(C)]
+pub struct XSetWindowAttributes {
+    pub background_pixmap: c_ulong,
+    pub background_pixel: c_ulong,
+    pub border_pixmap: c_ulong,
+    pub border_pixel: c_ulong,
+    pub bit_gravity: c_int,
+    pub win_gravity: c_int,
+    pub backing_store: c_int,
+    pub backing_planes: c_ulong,
+    pub backing_pixel: c_ulong,
+    pub save_under: Bool,
+    pub event_mask: c_long,
+    pub do_not_propagate_mask: c_long,
+    pub override_redirect: Bool,
+    pub colormap: c_ulong,
+    pub cursor: c_ulong,
+}
+
+#[repr(C)]
+pub struct XSizeHints {
+    pub flags: c_long,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub min_width: c_int,
+    pub min_height: c_int,
+    pub max_width: c_int,
+    pub max_height: c_int,
+    pub width_inc: c_int,
+    pub height_inc: c_int,
+    pub min_aspect: [c_int; 2],
+    pub max_aspect: [c_int; 2],
+    pub base_width: c_int,
+    pub base_height: c_int,
+    pub win_gravity: c_int,
+}
+
+// Only the leading fields are declared, the image itself is always allocated by Xlib.
+#[repr(C)]
+pub struct XImage {
+    pub width: c_int,
+    pub height: c_int,
+    pub xoffset: c_int,
+    pub format: c_int,
+    pub data: *mut c_char,
+    pub byte_order: c_int,
+    pub bitmap_unit: c_int,
+    pub bitmap_bit_order: c_int,
+    pub bitmap_pad: c_int,
+    pub depth: c_int,
+    pub bytes_per_line: c_int,
+    pub bits_per_pixel: c_int,
+    pub red_mask: c_ulong,
+    pub green_mask: c_ulong,
+    pub blue_mask: c_ulong,
+}
+
+#[repr(C)]
+pub struct XCharStruct {
+    pub lbearing: c_short,
+    pub rbearing: c_short,
+    pub width: c_short,
+    pub ascent: c_short,
+    pub descent: c_short,
+    pub attributes: c_ushort,
+}
+
+#[repr(C)]
+pub struct XFontStruct {
+    pub ext_data: *mut c_void,
+    pub fid: c_ulong,
+    pub direction: c_uint,
+    pub min_char_or_byte2: c_uint,
+    pub max_char_or_byte2: c_uint,
+    pub min_byte1: c_uint,
+    pub max_byte1: c_uint,
+    pub all_chars_exist: Bool,
+    pub default_char: c_uint,
+    pub n_properties: c_int,
+    pub properties: *mut c_void,
+    pub min_bounds: XCharStruct,
+    pub max_bounds: XCharStruct,
+    pub per_char: *mut XCharStruct,
+    pub ascent: c_int,
+    pub descent: c_int,
+}
+
+#[repr(C)]
+pub struct XShmSegmentInfo {
+    pub shmseg: c_ulong,
+    pub shmid: c_int,
+    pub shmaddr: *mut c_char,
+    pub read_only: Bool,
+}
+
+#[link(name = "X11")]
+extern "C" {
+    pub fn XOpenDisplay(name: *const c_char) -> *mut Display;
+    pub fn XCloseDisplay(display: *mut Display) -> c_int;
+    pub fn XDefaultScreen(display: *mut Display) -> c_int;
+    pub fn XDefaultVisual(display: *mut Display, screen: c_int) -> *mut Visual;
+    pub fn XDefaultDepth(display: *mut Display, screen: c_int) -> c_int;
+    pub fn XRootWindow(display: *mut Display, screen: c_int) -> Window;
+    pub fn XBlackPixel(display: *mut Display, screen: c_int) -> c_ulong;
+    pub fn XWhitePixel(display: *mut Display, screen: c_int) -> c_ulong;
+    pub fn XConnectionNumber(display: *mut Display) -> c_int;
+    pub fn XDisplayWidth(display: *mut Display, screen: c_int) -> c_int;
+    pub fn XDisplayHeight(display: *mut Display, screen: c_int) -> c_int;
+    pub fn XSetErrorHandler(handler: Option<XErrorHandler>) -> Option<XErrorHandler>;
+
+    pub fn XCreateSimpleWindow(display: *mut Display, parent: Window, x: c_int, y: c_int, width: c_uint, height: c_uint, border_width: c_uint, border: c_ulong, background: c_ulong) -> Window;
+    pub fn XCreateWindow(
+        display: *mut Display,
+        parent: Window,
+        x: c_int,
+        y: c_int,
+        width: c_uint,
+        height: c_uint,
+        border_width: c_uint,
+        depth: c_int,
+        class: c_uint,
+        visual: *mut Visual,
+        value_mask: c_ulong,
+        attributes: *mut XSetWindowAttributes,
+    ) -> Window;
+    pub fn XDestroyWindow(display: *mut Display, window: Window) -> c_int;
+    pub fn XMapWindow(display: *mut Display, window: Window) -> c_int;
+    pub fn XResizeWindow(display: *mut Display, window: Window, width: c_uint, height: c_uint) -> c_int;
+    pub fn XMapRaised(display: *mut Display, window: Window) -> c_int;
+    pub fn XStoreName(display: *mut Display, window: Window, name: *const c_char) -> c_int;
+    pub fn XSelectInput(display: *mut Display, window: Window, event_mask: c_long) -> c_int;
+    pub fn XSetWMNormalHints(display: *mut Display, window: Window, hints: *mut XSizeHints);
+    pub fn XInternAtom(display: *mut Display, name: *const c_char, only_if_exists: Bool) -> Atom;
+    pub fn XSetWMProtocols(display: *mut Display, window: Window, protocols: *mut Atom, count: c_int) -> Status;
+
+    pub fn XPending(display: *mut Display) -> c_int;
+    pub fn XNextEvent(display: *mut Display, event: *mut XEvent) -> c_int;
+    pub fn XPutBackEvent(display: *mut Display, event: *mut XEvent) -> c_int;
+    pub fn XFlush(display: *mut Display) -> c_int;
+    pub fn XSync(display: *mut Display, discard: Bool) -> c_int;
+
+    pub fn XGrabPointer(display: *mut Display, window: Window, owner_events: Bool, event_mask: c_uint, pointer_mode: c_int, keyboard_mode: c_int, confine_to: Window, cursor: c_ulong, time: c_ulong) -> c_int;
+    pub fn XUngrabPointer(display: *mut Display, time: c_ulong) -> c_int;
+    pub fn XQueryPointer(
+        display: *mut Display,
+        window: Window,
+        root: *mut Window,
+        child: *mut Window,
+        root_x: *mut c_int,
+        root_y: *mut c_int,
+        win_x: *mut c_int,
+        win_y: *mut c_int,
+        mask: *mut c_uint,
+    ) -> Bool;
+
+    pub fn XLookupKeysym(event: *const XKeyEvent, index: c_int) -> KeySym;
+    pub fn XKeysymToKeycode(display: *mut Display, keysym: KeySym) -> XKeyCode;
+    pub fn XQueryKeymap(display: *mut Display, keys: *mut c_char) -> c_int;
+    pub fn XkbSetDetectableAutoRepeat(display: *mut Display, detectable: Bool, supported: *mut Bool) -> Bool;
+
+    pub fn XCreateGC(display: *mut Display, drawable: Drawable, value_mask: c_ulong, values: *mut c_void) -> GC;
+    pub fn XFreeGC(display: *mut Display, gc: GC) -> c_int;
+    pub fn XSetForeground(display: *mut Display, gc: GC, color: c_ulong) -> c_int;
+    pub fn XSetFont(display: *mut Display, gc: GC, font: c_ulong) -> c_int;
+    pub fn XFillRectangle(display: *mut Display, drawable: Drawable, gc: GC, x: c_int, y: c_int, width: c_uint, height: c_uint) -> c_int;
+    pub fn XDrawLine(display: *mut Display, drawable: Drawable, gc: GC, x1: c_int, y1: c_int, x2: c_int, y2: c_int) -> c_int;
+    pub fn XDrawString(display: *mut Display, drawable: Drawable, gc: GC, x: c_int, y: c_int, string: *const c_char, length: c_int) -> c_int;
+    pub fn XLoadQueryFont(display: *mut Display, name: *const c_char) -> *mut XFontStruct;
+    pub fn XFreeFont(display: *mut Display, font: *mut XFontStruct) -> c_int;
+    pub fn XTextWidth(font: *mut XFontStruct, string: *const c_char, count: c_int) -> c_int;
+
+    pub fn XCreateImage(display: *mut Display, visual: *mut Visual, depth: c_uint, format: c_int, offset: c_int, data: *mut c_char, width: c_uint, height: c_uint, bitmap_pad: c_int, bytes_per_line: c_int) -> *mut XImage;
+    pub fn XPutImage(display: *mut Display, drawable: Drawable, gc: GC, image: *mut XImage, src_x: c_int, src_y: c_int, dest_x: c_int, dest_y: c_int, width: c_uint, height: c_uint) -> c_int;
+    pub fn XFree(data: *mut c_void) -> c_int;
+}
+
+#[link(name = "Xext")]
+extern "C" {
+    pub fn XShmQueryExtension(display: *mut Display) -> Bool;
+    pub fn XShmCreateImage(display: *mut Display, visual: *mut Visual, depth: c_uint, format: c_int, data: *mut c_char, shminfo: *mut XShmSegmentInfo, width: c_uint, height: c_uint) -> *mut XImage;
+    pub fn XShmAttach(display: *mut Display, shminfo: *mut XShmSegmentInfo) -> Bool;
+    pub fn XShmDetach(display: *mut Display, shminfo: *mut XShmSegmentInfo) -> Bool;
+    pub fn XShmPutImage(display: *mut Display, drawable: Drawable, gc: GC, image: *mut XImage, src_x: c_int, src_y: c_int, dest_x: c_int, dest_y: c_int, width: c_uint, height: c_uint, send_event: Bool) -> Bool;
+}
+
+pub fn to_cstring(s: &str) -> Vec<c_char> {
+    return s.bytes().map(|b| b as c_char).chain(Some(0)).collect();
+}
+
+pub const FALSE: Bool = 0;
+pub const TRUE: Bool = 1;
+
+pub const KEY_PRESS: c_int = 2;
+pub const KEY_RELEASE: c_int = 3;
+pub const BUTTON_PRESS: c_int = 4;
+pub const BUTTON_RELEASE: c_int = 5;
+pub const MOTION_NOTIFY: c_int = 6;
+pub const LEAVE_NOTIFY: c_int = 8;
+pub const EXPOSE: c_int = 12;
+pub const CLIENT_MESSAGE: c_int = 33;
+
+pub const KEY_PRESS_MASK: c_long = 1 << 0;
+pub const KEY_RELEASE_MASK: c_long = 1 << 1;
+pub const BUTTON_PRESS_MASK: c_long = 1 << 2;
+pub const BUTTON_RELEASE_MASK: c_long = 1 << 3;
+pub const LEAVE_WINDOW_MASK: c_long = 1 << 5;
+pub const POINTER_MOTION_MASK: c_long = 1 << 6;
+pub const EXPOSURE_MASK: c_long = 1 << 15;
+
+pub const BUTTON_1: c_uint = 1;
+pub const BUTTON_3: c_uint = 3;
+
+pub const P_MIN_SIZE: c_long = 1 << 4;
+pub const P_MAX_SIZE: c_long = 1 << 5;
+
+pub const CW_BACK_PIXEL: c_ulong = 1 << 1;
+pub const CW_BORDER_PIXEL: c_ulong = 1 << 3;
+pub const CW_OVERRIDE_REDIRECT: c_ulong = 1 << 9;
+pub const CW_SAVE_UNDER: c_ulong = 1 << 10;
+pub const CW_EVENT_MASK: c_ulong = 1 << 11;
+
+pub const INPUT_OUTPUT: c_uint = 1;
+pub const COPY_FROM_PARENT: c_int = 0;
+pub const GRAB_MODE_ASYNC: c_int = 1;
+pub const GRAB_SUCCESS: c_int = 0;
+pub const CURRENT_TIME: c_ulong = 0;
+pub const Z_PIXMAP: c_int = 2;
+pub const LSB_FIRST: c_int = 0;